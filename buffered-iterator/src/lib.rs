@@ -75,13 +75,11 @@ where
     }
 
     /// Peeks at the `i`th element of the iterator.
-
     pub fn peeki(&mut self, i: usize) -> Option<&I::Item> {
         self.peekn(i + 1).get(i)
     }
 
     /// Tries to find the first index that matches `predicate`.
-
     pub fn findi<P>(&mut self, predicate: P) -> Option<usize>
     where
         P: Fn(&I::Item) -> bool,
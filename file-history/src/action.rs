@@ -1,8 +1,16 @@
+#[cfg(feature = "tags")]
+use crate::tags::{extract_cover, write_tag_values, TagValue};
+use crate::trash::{
+    check_same_device, restore, same_device, trash, trash_name, trashed_path,
+};
 use crate::{HistoryError, Result};
 use log::trace;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::{fmt, fs};
+use std::{fmt, fs, io};
 
 /// Action is responsible for doing and undoing filesystem operations
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Hash)]
@@ -21,6 +29,27 @@ impl fmt::Display for Action {
                 source.display(),
                 target.display(), indent=indent
             )?,
+            ActionType::Copy { source, target } => write!(
+                f,
+                "Action::Copy {{\n{indent}source: \"{}\",\n{indent}target: \"{}\"\n}}",
+                source.display(),
+                target.display(), indent=indent
+            )?,
+            ActionType::HardLink { source, target } => write!(
+                f,
+                "Action::HardLink {{\n{indent}source: \"{}\",\n{indent}target: \"{}\"\n}}",
+                source.display(),
+                target.display(), indent=indent
+            )?,
+            ActionType::SymLink { source, target } => write!(
+                f,
+                "Action::SymLink {{\n{indent}source: \"{}\",\n{indent}target: \"{}\"\n}}",
+                source.display(),
+                target.display(), indent=indent
+            )?,
+            ActionType::WriteFile { path, .. } => write!(
+                f, "Action::WriteFile(\n{}\"{}\"\n)", indent, path.display()
+            )?,
             ActionType::MkDir(path) => {
                 write!(
                     f, "Action::MakeDir(\n{}\"{}\"\n)", indent, path.display()
@@ -31,6 +60,37 @@ impl fmt::Display for Action {
                     f, "Action::RemoveDir(\n{}\"{}\"\n)",indent,  path.display()
                 )?;
             }
+            ActionType::RemoveFile { path, stash } => write!(
+                f,
+                "Action::RemoveFile {{\n{indent}path: \"{}\",\n{indent}stash: \"{}\"\n}}",
+                path.display(),
+                stash.display(), indent=indent
+            )?,
+            ActionType::Trash { path, trash_directory, name } => write!(
+                f,
+                "Action::Trash {{\n{indent}path: \"{}\",\n{indent}trashed: \"{}\"\n}}",
+                path.display(),
+                trashed_path(trash_directory, name).display(), indent=indent
+            )?,
+            #[cfg(feature = "tags")]
+            ActionType::UpdateTags { path, tag_values, .. } => write!(
+                f,
+                "Action::UpdateTags {{\n{indent}path: \"{}\",\n{indent}keys: [{}]\n}}",
+                path.display(),
+                tag_values.iter().map(TagValue::key).collect::<Vec<_>>().join(", "),
+                indent=indent
+            )?,
+            #[cfg(feature = "tags")]
+            ActionType::ConvertTags { path, .. } => write!(
+                f, "Action::ConvertTags(\n{}\"{}\"\n)", indent, path.display()
+            )?,
+            #[cfg(feature = "tags")]
+            ActionType::ExtractCover { source, target } => write!(
+                f,
+                "Action::ExtractCover {{\n{indent}source: \"{}\",\n{indent}target: \"{}\"\n}}",
+                source.display(),
+                target.display(), indent=indent
+            )?,
         }
 
         write!(
@@ -61,6 +121,84 @@ impl Action {
         }
     }
 
+    /// Create new `Copy` Action
+    pub fn copy<P, Q>(source: P, target: Q) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let action_type = ActionType::Copy {
+            source: source.as_ref().to_owned(),
+            target: target.as_ref().to_owned(),
+        };
+
+        Self {
+            action_type,
+            applied: false,
+        }
+    }
+
+    /// Create new `HardLink` Action. Fails if `target` is on another
+    /// filesystem than `source`, so no link is attempted that can't succeed.
+    pub fn hard_link<P, Q>(source: P, target: Q) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let source = source.as_ref().to_owned();
+        let target = target.as_ref().to_owned();
+
+        if !same_device(&source, &target)? {
+            return Err(HistoryError::LinkOnOtherDevice(source, target));
+        }
+
+        let action_type = ActionType::HardLink { source, target };
+
+        Ok(Self {
+            action_type,
+            applied: false,
+        })
+    }
+
+    /// Create new `SymLink` Action
+    pub fn symlink<P, Q>(source: P, target: Q) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let action_type = ActionType::SymLink {
+            source: source.as_ref().to_owned(),
+            target: target.as_ref().to_owned(),
+        };
+
+        Self {
+            action_type,
+            applied: false,
+        }
+    }
+
+    /// Create new `WriteFile` Action. `previous` holds the contents that
+    /// `current` replaces, or `None` if the file is new.
+    pub fn write_file<P>(
+        path: P,
+        previous: Option<String>,
+        current: String,
+    ) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let action_type = ActionType::WriteFile {
+            path: path.as_ref().to_owned(),
+            previous,
+            current,
+        };
+
+        Self {
+            action_type,
+            applied: false,
+        }
+    }
+
     /// Create new `MakeDir` Action
     pub fn mkdir<P>(target: P) -> Self
     where
@@ -87,6 +225,113 @@ impl Action {
         }
     }
 
+    /// Create new `RemoveFile` Action. The file is kept in `stash_directory`
+    /// under the hash of its contents, so the removal can be undone.
+    pub fn remove_file<P, Q>(path: P, stash_directory: Q) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let path = path.as_ref().to_owned();
+        let stash = stash_directory.as_ref().join(content_hash(&path)?);
+
+        let action_type = ActionType::RemoveFile { path, stash };
+
+        Ok(Self {
+            action_type,
+            applied: false,
+        })
+    }
+
+    /// Create new `Trash` Action, which moves a file or directory to
+    /// `trash_directory` according to the freedesktop.org trash
+    /// specification. Fails if `trash_directory` is on another filesystem.
+    pub fn trash<P, Q>(path: P, trash_directory: Q) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        // The info file requires an absolute path. Joining an absolute path
+        // replaces the current directory.
+        let path = std::env::current_dir()?.join(path);
+        let trash_directory = trash_directory.as_ref().to_owned();
+        check_same_device(&trash_directory, &path)?;
+        let name = trash_name(&trash_directory, &path)?;
+
+        let action_type = ActionType::Trash {
+            path,
+            trash_directory,
+            name,
+        };
+
+        Ok(Self {
+            action_type,
+            applied: false,
+        })
+    }
+
+    /// Create new `UpdateTags` Action. `previous` holds the values of the
+    /// keys in `tag_values` before the update, which undoing writes back.
+    #[cfg(feature = "tags")]
+    pub fn update_tags<P>(
+        path: P,
+        previous: Vec<TagValue>,
+        tag_values: Vec<TagValue>,
+    ) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let action_type = ActionType::UpdateTags {
+            path: path.as_ref().to_owned(),
+            tag_values,
+            previous,
+        };
+
+        Self {
+            action_type,
+            applied: false,
+        }
+    }
+
+    /// Create new `ConvertTags` Action. `stash` is a file in the stash
+    /// directory that holds a copy of the file at `path` with the new tag
+    /// layout. Applying and undoing swap the two files, so the stash always
+    /// holds the version that isn't at `path`.
+    #[cfg(feature = "tags")]
+    pub fn convert_tags<P, Q>(path: P, stash: Q) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let action_type = ActionType::ConvertTags {
+            path: path.as_ref().to_owned(),
+            stash: stash.as_ref().to_owned(),
+        };
+
+        Self {
+            action_type,
+            applied: false,
+        }
+    }
+
+    /// Create new `ExtractCover` Action
+    #[cfg(feature = "tags")]
+    pub fn extract_cover<P, Q>(source: P, target: Q) -> Self
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let action_type = ActionType::ExtractCover {
+            source: source.as_ref().to_owned(),
+            target: target.as_ref().to_owned(),
+        };
+
+        Self {
+            action_type,
+            applied: false,
+        }
+    }
+
     pub(crate) fn action_type(&self) -> &ActionType {
         &self.action_type
     }
@@ -115,12 +360,15 @@ impl Action {
     ///
     /// # Panics
     ///
-    /// This function panics if this action's type is not `Action::Move`
+    /// This function panics if this action's type is not `Action::Move`,
+    /// `Action::Copy`, `Action::HardLink` or `Action::SymLink`
     pub fn get_src_tgt_unchecked(&self) -> (&Path, &Path) {
-        if let ActionType::Mv { source, target } = self.action_type() {
-            (source, target)
-        } else {
-            panic!("Current Action is not Action::Move!")
+        match self.action_type() {
+            ActionType::Mv { source, target }
+            | ActionType::Copy { source, target }
+            | ActionType::HardLink { source, target }
+            | ActionType::SymLink { source, target } => (source, target),
+            _ => panic!("Current Action does not have a source and target!"),
         }
     }
 }
@@ -137,10 +385,82 @@ pub enum ActionType {
         /// Target path
         target: PathBuf,
     },
+    /// Represents the copying of a file.
+    Copy {
+        /// Source path
+        source: PathBuf,
+        /// Target path
+        target: PathBuf,
+    },
+    /// Represents the creating of a hard link to a file.
+    HardLink {
+        /// Existing file
+        source: PathBuf,
+        /// Path of the link
+        target: PathBuf,
+    },
+    /// Represents the creating of a symbolic link to a file.
+    SymLink {
+        /// Existing file
+        source: PathBuf,
+        /// Path of the link
+        target: PathBuf,
+    },
+    /// Represents the writing of a text file.
+    WriteFile {
+        /// Path of the file
+        path: PathBuf,
+        /// Contents before writing, or `None` if the file was created
+        previous: Option<String>,
+        /// Contents after writing
+        current: String,
+    },
     /// Represents the creating of a directory
     MkDir(PathBuf),
     /// Represents the deletion of a directory
     RmDir(PathBuf),
+    /// Represents the removal of a file, which is kept in a stash.
+    RemoveFile {
+        /// Path of the file
+        path: PathBuf,
+        /// Path of the stash entry that holds its contents
+        stash: PathBuf,
+    },
+    /// Represents moving a file or directory to the trash.
+    Trash {
+        /// Original path
+        path: PathBuf,
+        /// Trash directory, e.g. `~/.local/share/Trash`
+        trash_directory: PathBuf,
+        /// Name in the trash
+        name: OsString,
+    },
+    #[cfg(feature = "tags")]
+    /// Represents the updating of the tags of a file.
+    UpdateTags {
+        /// Path of the audio file
+        path: PathBuf,
+        /// Values after the update
+        tag_values: Vec<TagValue>,
+        /// Values before the update
+        previous: Vec<TagValue>,
+    },
+    #[cfg(feature = "tags")]
+    /// Represents replacing the tag blocks of a file with a single block.
+    ConvertTags {
+        /// Path of the audio file
+        path: PathBuf,
+        /// Path of the stash entry that holds the other version of the file
+        stash: PathBuf,
+    },
+    #[cfg(feature = "tags")]
+    /// Represents writing the front cover of an audio file to a new file.
+    ExtractCover {
+        /// Path of the audio file
+        source: PathBuf,
+        /// Path of the image
+        target: PathBuf,
+    },
 }
 impl ActionType {
     /// Applies the action
@@ -156,6 +476,56 @@ impl ActionType {
                 );
             }
 
+            ActionType::Copy { source, target } => {
+                // fs::copy overwrites existing files.
+                if target.exists() {
+                    return Err(
+                        io::Error::from(io::ErrorKind::AlreadyExists).into()
+                    );
+                }
+
+                fs::copy(source, target)?;
+
+                trace!(
+                    "Copied:\n\"{}\"\n\"{}\"",
+                    source.display(),
+                    target.display()
+                );
+            }
+
+            ActionType::HardLink { source, target } => {
+                fs::hard_link(source, target)?;
+
+                trace!(
+                    "Linked:\n\"{}\"\n\"{}\"",
+                    source.display(),
+                    target.display()
+                );
+            }
+
+            ActionType::SymLink { source, target } => {
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(source, target)?;
+
+                #[cfg(windows)]
+                std::os::windows::fs::symlink_file(source, target)?;
+
+                trace!(
+                    "Symlinked:\n\"{}\"\n\"{}\"",
+                    source.display(),
+                    target.display()
+                );
+            }
+
+            ActionType::WriteFile {
+                path,
+                previous,
+                current,
+            } => {
+                ActionType::write_file(path, previous.is_some(), current)?;
+                trace!("Wrote {}", path.display());
+            }
+
             ActionType::MkDir(path) => {
                 fs::create_dir(path)?;
                 trace!("Created directory {}", path.display());
@@ -165,6 +535,44 @@ impl ActionType {
                 fs::remove_dir(path)?;
                 trace!("Removed directory {}", path.display());
             }
+
+            ActionType::RemoveFile { path, stash } => {
+                ActionType::stash_file(path, stash)?;
+                trace!("Removed file {}", path.display());
+            }
+
+            ActionType::Trash {
+                path,
+                trash_directory,
+                name,
+            } => {
+                trash(trash_directory, name, path)?;
+                trace!("Trashed {}", path.display());
+            }
+
+            #[cfg(feature = "tags")]
+            ActionType::UpdateTags {
+                path, tag_values, ..
+            } => {
+                write_tag_values(path, tag_values)?;
+                trace!("Updated tags of {}", path.display());
+            }
+
+            #[cfg(feature = "tags")]
+            ActionType::ConvertTags { path, stash } => {
+                ActionType::swap_files(path, stash)?;
+                trace!("Converted tags of {}", path.display());
+            }
+
+            #[cfg(feature = "tags")]
+            ActionType::ExtractCover { source, target } => {
+                extract_cover(source, target)?;
+                trace!(
+                    "Extracted cover:\n\"{}\"\n\"{}\"",
+                    source.display(),
+                    target.display()
+                );
+            }
         }
         Ok(())
     }
@@ -182,6 +590,25 @@ impl ActionType {
                 );
             }
 
+            // Only the copy or link is removed, the source is left alone.
+            ActionType::Copy { target, .. }
+            | ActionType::HardLink { target, .. }
+            | ActionType::SymLink { target, .. } => {
+                fs::remove_file(target)?;
+
+                trace!("Removed {}", target.display());
+            }
+
+            ActionType::WriteFile { path, previous, .. } => {
+                if let Some(previous) = previous {
+                    fs::write(path, previous)?;
+                } else {
+                    fs::remove_file(path)?;
+                }
+
+                trace!("Restored {}", path.display());
+            }
+
             ActionType::MkDir(path) => {
                 fs::remove_dir(path)?;
 
@@ -193,10 +620,93 @@ impl ActionType {
 
                 trace!("Recreated directory {}", path.display());
             }
+
+            ActionType::RemoveFile { path, stash } => {
+                // Other actions may refer to the same stash entry, so it is
+                // left alone.
+                fs::copy(stash, path)?;
+
+                trace!("Restored file {}", path.display());
+            }
+
+            ActionType::Trash {
+                path,
+                trash_directory,
+                name,
+            } => {
+                restore(trash_directory, name, path)?;
+
+                trace!("Restored {} from trash", path.display());
+            }
+
+            #[cfg(feature = "tags")]
+            ActionType::UpdateTags { path, previous, .. } => {
+                write_tag_values(path, previous)?;
+
+                trace!("Restored tags of {}", path.display());
+            }
+
+            #[cfg(feature = "tags")]
+            ActionType::ConvertTags { path, stash } => {
+                ActionType::swap_files(path, stash)?;
+
+                trace!("Restored tags of {}", path.display());
+            }
+
+            #[cfg(feature = "tags")]
+            ActionType::ExtractCover { target, .. } => {
+                fs::remove_file(target)?;
+
+                trace!("Removed cover {}", target.display());
+            }
         }
         Ok(())
     }
 
+    /// Writes `contents` to `path`. Unless `overwrite` is set, `path` must
+    /// not exist, so a file that was created since isn't overwritten.
+    fn write_file(path: &Path, overwrite: bool, contents: &str) -> Result<()> {
+        if overwrite {
+            fs::write(path, contents)?;
+        } else {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)?
+                .write_all(contents.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves `path` to `stash`. If `stash` already exists, it holds the same
+    /// contents and `path` is removed instead.
+    fn stash_file(path: &Path, stash: &Path) -> Result<()> {
+        if stash.is_file() {
+            fs::remove_file(path)?;
+        } else {
+            if let Some(parent) = stash.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            ActionType::copy_or_move_file(path, stash)?;
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the contents of `path` and `stash`.
+    #[cfg(feature = "tags")]
+    fn swap_files(path: &Path, stash: &Path) -> Result<()> {
+        let swap = stash.with_extension("swap");
+
+        ActionType::copy_or_move_file(path, &swap)?;
+        ActionType::copy_or_move_file(stash, path)?;
+        fs::rename(swap, stash)?;
+
+        Ok(())
+    }
+
     fn copy_or_move_file(source: &Path, target: &Path) -> Result<()> {
         if let Err(err) = fs::rename(source, target) {
             // Can't rename across filesystem boundaries. Checks for
@@ -213,10 +723,15 @@ impl ActionType {
                 let expected_error_code = 18;
 
                 if expected_error_code == error_code {
-                    fs::copy(source, target)?;
-                    fs::remove_file(source)?;
+                    if source.is_dir() {
+                        ActionType::copy_directory(source, target)?;
+                        fs::remove_dir_all(source)?;
+                    } else {
+                        fs::copy(source, target)?;
+                        fs::remove_file(source)?;
+                    }
                     return Ok(());
-                };
+                }
             }
 
             Err(err.into())
@@ -224,6 +739,32 @@ impl ActionType {
             Ok(())
         }
     }
+
+    /// Copies the directory `source` and everything in it to `target`.
+    fn copy_directory(source: &Path, target: &Path) -> Result<()> {
+        fs::create_dir(target)?;
+
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            let target = target.join(entry.file_name());
+
+            if entry.file_type()?.is_dir() {
+                ActionType::copy_directory(&entry.path(), &target)?;
+            } else {
+                fs::copy(entry.path(), target)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the SHA-256 hash of the contents of `path` as a hex string.
+fn content_hash(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 #[cfg(test)]
@@ -311,6 +852,170 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_copy_directory() -> Result<()> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source");
+        let target = dir.child("target");
+
+        source.child("cover.jpg").write_str("cover")?;
+        source
+            .child("scans")
+            .child("front.png")
+            .write_str("front")?;
+
+        ActionType::copy_directory(&source, &target)?;
+
+        target.child("cover.jpg").assert("cover");
+        target.child("scans").child("front.png").assert("front");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_and_links() -> Result<()> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source");
+
+        source.write_str("contents")?;
+
+        let mut actions = [
+            Action::copy(&source, dir.child("copy")),
+            Action::hard_link(&source, dir.child("hard_link"))?,
+            Action::symlink(&source, dir.child("symlink")),
+        ];
+
+        for action in &mut actions {
+            action.apply()?;
+
+            // Applied: source and target exist
+            let (_, target) = action.get_src_tgt_unchecked();
+            source.assert("contents");
+            assert_eq!(fs::read_to_string(target)?, "contents");
+
+            action.undo()?;
+
+            // Undone: only the source exists
+            let (_, target) = action.get_src_tgt_unchecked();
+            source.assert("contents");
+            assert!(fs::symlink_metadata(target).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_hard_link_other_device() -> Result<()> {
+        let dir = TempDir::new()?;
+        let source = dir.child("source");
+
+        source.touch()?;
+
+        // procfs is never on the same filesystem as a temporary directory.
+        assert!(matches!(
+            Action::hard_link(&source, "/proc/hard_link"),
+            Err(HistoryError::LinkOnOtherDevice(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_file() -> Result<()> {
+        let dir = TempDir::new()?;
+        let existing = dir.child("existing.m3u8");
+        let new = dir.child("new.m3u8");
+
+        existing.write_str("before")?;
+
+        let mut update = Action::write_file(
+            &existing,
+            Some("before".to_owned()),
+            "after".to_owned(),
+        );
+        let mut create = Action::write_file(&new, None, "created".to_owned());
+
+        update.apply()?;
+        create.apply()?;
+
+        // Applied: both have their new contents
+        existing.assert("after");
+        new.assert("created");
+
+        create.undo()?;
+        update.undo()?;
+
+        // Undone: the existing file is restored, the new one is gone
+        existing.assert("before");
+        new.assert(predicate::path::missing());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_file() -> Result<()> {
+        let dir = TempDir::new()?;
+        let stash = dir.child("stash");
+        let first = dir.child("first");
+        let second = dir.child("second");
+
+        first.write_str("contents")?;
+        second.write_str("contents")?;
+
+        let mut first_action = Action::remove_file(&first, &stash)?;
+        let mut second_action = Action::remove_file(&second, &stash)?;
+
+        first_action.apply()?;
+        second_action.apply()?;
+
+        // Applied: both are gone, their contents are stashed once
+        first.assert(predicate::path::missing());
+        second.assert(predicate::path::missing());
+        assert_eq!(fs::read_dir(&stash)?.count(), 1);
+
+        second_action.undo()?;
+        first_action.undo()?;
+
+        // Undone: both are restored
+        first.assert("contents");
+        second.assert("contents");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash() -> Result<()> {
+        let dir = TempDir::new()?;
+        let trash_directory = dir.child("Trash");
+        let path = dir.child("album");
+
+        path.child("cover.jpg").touch()?;
+
+        let mut action = Action::trash(&path, &trash_directory)?;
+
+        action.apply()?;
+
+        // Applied: in the trash, with an info file
+        path.assert(predicate::path::missing());
+        trash_directory
+            .child("files/album/cover.jpg")
+            .assert(predicate::path::exists());
+        trash_directory
+            .child("info/album.trashinfo")
+            .assert(predicate::path::exists());
+
+        action.undo()?;
+
+        // Undone: restored, without an info file
+        path.child("cover.jpg").assert(predicate::path::exists());
+        trash_directory
+            .child("info/album.trashinfo")
+            .assert(predicate::path::missing());
+
+        Ok(())
+    }
+
     #[test]
     fn test_apply_twice() -> Result<()> {
         let dir = TempDir::new()?;
@@ -331,4 +1036,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(feature = "tags")]
+    #[test]
+    fn test_update_tags() -> Result<()> {
+        use lofty::TagType;
+
+        let dir = TempDir::new()?;
+        let path = dir.child("track.mp3");
+
+        path.write_binary(&fs::read(
+            "../tests/testdata/music/Under Siege - Amon Amarth.mp3",
+        )?)?;
+
+        let original = fs::read(&path)?;
+
+        // The file has no APE tag, so a new block is added.
+        let mut action = Action::update_tags(
+            &path,
+            vec![TagValue::new(TagType::Ape, "Title", Vec::new())],
+            vec![TagValue::new(
+                TagType::Ape,
+                "Title",
+                vec!["Title".to_owned()],
+            )],
+        );
+
+        action.apply()?;
+
+        // Applied: the file changed
+        assert_ne!(fs::read(&path)?, original);
+
+        action.undo()?;
+
+        // Undone: the previous values are written back, and the empty APE
+        // tag is removed
+        assert_eq!(fs::read(&path)?, original);
+
+        Ok(())
+    }
 }
@@ -2,11 +2,74 @@ use crate::util::calculate_hash;
 use crate::{Action, ActionType, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::path::Path;
 
+/// The number of actions of each type in a group.
 pub struct ActionCount {
+    /// Moved files
     pub mv: u64,
+    /// Copied files
+    pub copy: u64,
+    /// Created hard links
+    pub hard_link: u64,
+    /// Created symbolic links
+    pub symlink: u64,
+    /// Created directories
     pub mkdir: u64,
+    /// Removed directories
     pub rmdir: u64,
+    /// Removed files
+    pub remove_file: u64,
+    /// Trashed files and directories
+    pub trash: u64,
+    /// Written text files
+    pub write_file: u64,
+    #[cfg(feature = "tags")]
+    /// Files with updated tags
+    pub update_tags: u64,
+    #[cfg(feature = "tags")]
+    /// Extracted covers
+    pub extract_cover: u64,
+}
+
+impl ActionCount {
+    /// Returns a description and the count of every action type.
+    pub fn counts(&self) -> Vec<(&'static str, u64)> {
+        #[cfg_attr(not(feature = "tags"), allow(unused_mut))]
+        let mut counts = vec![
+            ("moves", self.mv),
+            ("copies", self.copy),
+            ("hard links", self.hard_link),
+            ("symlinks", self.symlink),
+            ("dirs created", self.mkdir),
+            ("dirs removed", self.rmdir),
+            ("files removed", self.remove_file),
+            ("trashed", self.trash),
+            ("files written", self.write_file),
+        ];
+
+        #[cfg(feature = "tags")]
+        counts.extend([
+            ("tag updates", self.update_tags),
+            ("covers extracted", self.extract_cover),
+        ]);
+
+        counts
+    }
+}
+
+impl fmt::Display for ActionCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, count)) in self.counts().into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{count} {name}")?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -17,23 +80,31 @@ pub(crate) struct ActionGroup {
 
 impl fmt::Display for ActionGroup {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut move_count = 0;
-        let mut mkdir_count = 0;
-        let mut rmdir_count = 0;
+        let count = self.to_action_count();
 
-        for action in &self.actions {
-            match action.action_type() {
-                ActionType::Mv { .. } => move_count += 1,
-                ActionType::MkDir(_) => mkdir_count += 1,
-                ActionType::RmDir(_) => rmdir_count += 1,
-            }
-        }
+        write!(
+            f,
+            "mv: {}, copy: {}, hard link: {}, symlink: {}, mkdir: {}, rmdir: {}, remove file: {}, trash: {}, write file: {}",
+            count.mv,
+            count.copy,
+            count.hard_link,
+            count.symlink,
+            count.mkdir,
+            count.rmdir,
+            count.remove_file,
+            count.trash,
+            count.write_file
+        )?;
 
-        writeln!(
+        #[cfg(feature = "tags")]
+        write!(
             f,
-            "mv: {move_count}, mkdir: {mkdir_count}, rmdir: {rmdir_count}"
+            ", update tags: {}, extract cover: {}",
+            count.update_tags, count.extract_cover
         )?;
 
+        writeln!(f)?;
+
         for action in &self.actions {
             writeln!(f, "{action}")?;
         }
@@ -62,21 +133,58 @@ impl ActionGroup {
     pub(crate) fn to_action_count(&self) -> ActionCount {
         let mut action_count = ActionCount {
             mv: 0,
+            copy: 0,
+            hard_link: 0,
+            symlink: 0,
             mkdir: 0,
             rmdir: 0,
+            remove_file: 0,
+            trash: 0,
+            write_file: 0,
+            #[cfg(feature = "tags")]
+            update_tags: 0,
+            #[cfg(feature = "tags")]
+            extract_cover: 0,
         };
 
         for action in &self.actions {
             match action.action_type() {
                 ActionType::Mv { .. } => action_count.mv += 1,
+                ActionType::Copy { .. } => action_count.copy += 1,
+                ActionType::HardLink { .. } => action_count.hard_link += 1,
+                ActionType::SymLink { .. } => action_count.symlink += 1,
                 ActionType::MkDir(_) => action_count.mkdir += 1,
                 ActionType::RmDir(_) => action_count.rmdir += 1,
+                ActionType::RemoveFile { .. } => action_count.remove_file += 1,
+                ActionType::Trash { .. } => action_count.trash += 1,
+                ActionType::WriteFile { .. } => action_count.write_file += 1,
+                #[cfg(feature = "tags")]
+                ActionType::UpdateTags { .. }
+                | ActionType::ConvertTags { .. } => {
+                    action_count.update_tags += 1;
+                }
+                #[cfg(feature = "tags")]
+                ActionType::ExtractCover { .. } => {
+                    action_count.extract_cover += 1;
+                }
             }
         }
 
         action_count
     }
 
+    /// Returns the stash entries that the actions in this group refer to.
+    pub(crate) fn stash_entries(&self) -> impl Iterator<Item = &Path> {
+        self.actions
+            .iter()
+            .flat_map(|action| match action.action_type() {
+                ActionType::RemoveFile { stash, .. } => vec![stash.as_path()],
+                #[cfg(feature = "tags")]
+                ActionType::ConvertTags { stash, .. } => vec![stash.as_path()],
+                _ => Vec::new(),
+            })
+    }
+
     // pub(crate) fn to_string_short(&self) -> String {
     //     let string = self.to_string();
     //     let lines: Vec<&str> = string.lines().collect();
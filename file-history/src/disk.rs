@@ -30,7 +30,7 @@ impl DiskHandler {
 
     pub(crate) fn clear(&self) -> Result<bool> {
         match fs::remove_file(&self.path) {
-            Ok(_) => Ok(true),
+            Ok(()) => Ok(true),
             Err(err) => {
                 if err.kind() == ErrorKind::NotFound {
                     Ok(false)
@@ -1,13 +1,15 @@
 use crate::actiongroup::ActionCount;
 use crate::{Action, ActionGroup, DiskHandler, Result};
 use log::{debug, info};
-use std::fmt;
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs};
 
 /// History is responsible for saving and loading `ActionGroup`s
 #[derive(PartialEq, Debug)]
 pub struct History {
     disk_handler: DiskHandler,
+    stash_directory: PathBuf,
     current_group: ActionGroup,
     applied_groups: Vec<ActionGroup>,
     undone_groups: Vec<ActionGroup>,
@@ -34,6 +36,10 @@ impl fmt::Display for History {
 }
 
 impl History {
+    /// Name of the directory next to the history file that holds removed
+    /// files.
+    pub const STASH_NAME: &'static str = "stash";
+
     /// Load or create history file at `path`
     pub fn load(directory: &Path, name: &str) -> Result<Self> {
         let disk_handler = DiskHandler::init_dir(directory, name);
@@ -43,6 +49,7 @@ impl History {
 
         Ok(History {
             disk_handler,
+            stash_directory: directory.join(History::STASH_NAME),
             current_group: ActionGroup::new(),
             applied_groups,
             undone_groups,
@@ -89,6 +96,56 @@ impl History {
         self.disk_handler.path()
     }
 
+    /// Gets the directory that holds the contents of removed files.
+    pub fn stash_directory(&self) -> &Path {
+        &self.stash_directory
+    }
+
+    /// Returns the stash entries that no action in the history refers to.
+    pub fn stash_garbage(&self) -> Result<Vec<PathBuf>> {
+        let referenced: HashSet<&Path> = std::iter::once(&self.current_group)
+            .chain(&self.applied_groups)
+            .chain(&self.undone_groups)
+            .flat_map(ActionGroup::stash_entries)
+            .collect();
+
+        let entries = match fs::read_dir(&self.stash_directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Vec::new())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut garbage = Vec::new();
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if !referenced.contains(path.as_path()) {
+                garbage.push(path);
+            }
+        }
+
+        garbage.sort();
+
+        Ok(garbage)
+    }
+
+    /// Removes the stash entries that no action in the history refers to.
+    /// Returns the removed entries.
+    pub fn collect_garbage(&self) -> Result<Vec<PathBuf>> {
+        let garbage = self.stash_garbage()?;
+
+        for path in &garbage {
+            fs::remove_file(path)?;
+        }
+
+        info!("Removed {} stash entries.", garbage.len());
+
+        Ok(garbage)
+    }
+
     /// Apply an action to the current `ActionGroup`.
     pub fn apply(&mut self, action: Action) -> Result<()> {
         self.current_group.apply(action)?;
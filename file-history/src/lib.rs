@@ -4,7 +4,11 @@
 #![allow(clippy::must_use_candidate)]
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::missing_panics_doc)]
-//! This crate tracks moving of files and creation and deletion of folders in a reversible manner.
+//! This crate tracks moving, copying, linking, writing, removing and trashing
+//! of files and creation and deletion of folders in a reversible manner.
+//!
+//! With the `tags` feature, it also tracks updating the tags of audio files
+//! and extracting their covers.
 
 #[cfg(all(feature = "bincode", feature = "serde_json"))]
 compile_error!("bincode and serde_json are mutually exclusive!");
@@ -16,6 +20,9 @@ pub mod history;
 
 mod actiongroup;
 mod disk;
+#[cfg(feature = "tags")]
+mod tags;
+mod trash;
 mod util;
 
 use std::path::PathBuf;
@@ -27,7 +34,14 @@ use thiserror::Error;
 pub(crate) use action::ActionType;
 
 pub use action::Action;
+pub use actiongroup::ActionCount;
 pub use history::History;
+#[cfg(feature = "tags")]
+pub use tags::{
+    front_cover, read_split_tag, tag_type_from_name, tag_type_name,
+    TagRemainder, TagValue, TAG_TYPES,
+};
+pub use trash::home_trash_directory;
 
 /// Wrapper for Result
 pub type Result<T> = std::result::Result<T, HistoryError>;
@@ -47,17 +61,49 @@ pub enum HistoryError {
     #[error("This action has already been undone: {0}")]
     NotYetApplied(Action),
 
-    /// Represents std::io::Error
+    /// Neither `$XDG_DATA_HOME` nor `$HOME` is set.
+    #[error("Unable to find the trash directory.")]
+    NoTrash,
+
+    /// Path can't be trashed, because the trash is on another filesystem.
+    #[error("Unable to move {0} to the trash, because the trash is on another filesystem.")]
+    TrashOnOtherDevice(PathBuf),
+
+    /// Path can't be hard linked, because the target is on another
+    /// filesystem.
+    #[error("Unable to hard link {0} to {1}, because they are on different filesystems.")]
+    LinkOnOtherDevice(PathBuf, PathBuf),
+
+    /// Path can't be trashed, because it has no file name.
+    #[error("Path has no file name: {0}")]
+    NoFileName(PathBuf),
+
+    /// Represents `std::io::Error`
     #[error("I/O error: {0}")]
     IO(#[from] std::io::Error),
 
+    #[cfg(feature = "tags")]
+    /// Represents `lofty::LoftyError`
+    #[error("Tag error: {0}")]
+    Lofty(#[from] lofty::LoftyError),
+
+    #[cfg(feature = "tags")]
+    /// Tag type in history is not recognized.
+    #[error("Unknown tag type: {0}")]
+    UnknownTagType(String),
+
+    #[cfg(feature = "tags")]
+    /// Audio file has no embedded pictures.
+    #[error("No cover in {0}")]
+    MissingCover(PathBuf),
+
     #[cfg(feature = "bincode")]
     /// Represents bincode::Error
     #[error("Bincode error: {0}")]
     Bincode(#[from] bincode::Error),
 
     #[cfg(feature = "serde_json")]
-    /// Represents serde_json::Error
+    /// Represents `serde_json::Error`
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 }
@@ -0,0 +1,502 @@
+use crate::{HistoryError, Result};
+use lofty::aac::AacFile;
+use lofty::id3::v2::{Frame, FrameFlags, FrameValue, Id3v2Tag};
+use lofty::iff::aiff::AiffFile;
+use lofty::iff::wav::WavFile;
+use lofty::mp4::{AtomData, AtomIdent, Ilst, Mp4File};
+use lofty::mpeg::MpegFile;
+use lofty::{
+    AudioFile, FileType, ItemKey, ItemValue, MergeTag, ParseOptions, Picture,
+    PictureType, Probe, SplitTag, Tag, TagExt, TagItem, TagType, TaggedFileExt,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Names of all tag blocks, in their default priority. These names are
+/// stored in the history and used on the command line.
+pub const TAG_TYPES: [(&str, TagType); 7] = [
+    ("id3v2", TagType::Id3v2),
+    ("mp4", TagType::Mp4Ilst),
+    ("vorbis", TagType::VorbisComments),
+    ("ape", TagType::Ape),
+    ("aiff", TagType::AiffText),
+    ("riff", TagType::RiffInfo),
+    ("id3v1", TagType::Id3v1),
+];
+
+/// Returns the tag block called `name`, ignoring case.
+pub fn tag_type_from_name(name: &str) -> Option<TagType> {
+    TAG_TYPES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, tag_type)| *tag_type)
+}
+
+/// Returns the name of `tag_type`.
+pub fn tag_type_name(tag_type: TagType) -> &'static str {
+    TAG_TYPES
+        .iter()
+        .find(|(_, t)| *t == tag_type)
+        .map_or("unknown", |(name, _)| *name)
+}
+
+// Order in which tag blocks are searched for a cover.
+const PICTURE_TAG_TYPES: [TagType; 4] = [
+    TagType::Id3v2,
+    TagType::Mp4Ilst,
+    TagType::VorbisComments,
+    TagType::Ape,
+];
+
+/// All values of a single item in a tag block.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Hash)]
+pub struct TagValue {
+    tag_type: String,
+    key: String,
+    values: Vec<String>,
+}
+
+impl TagValue {
+    /// Create a new `TagValue` for the item with the native `key` in the tag
+    /// block of `tag_type`. Empty `values` remove the item.
+    pub fn new(tag_type: TagType, key: &str, values: Vec<String>) -> Self {
+        TagValue {
+            tag_type: tag_type_name(tag_type).to_owned(),
+            key: key.to_owned(),
+            values,
+        }
+    }
+
+    /// Returns the tag block of this value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tag block was not recognized.
+    pub fn tag_type(&self) -> Result<TagType> {
+        tag_type_from_name(&self.tag_type)
+            .ok_or_else(|| HistoryError::UnknownTagType(self.tag_type.clone()))
+    }
+
+    /// Returns the native key of this value.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the values.
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+}
+
+/// The part of a tag block that a generic `Tag` can't hold, such as
+/// described comments in `ID3v2` or integer atoms in MP4. Merging a `Tag` back
+/// into it gives the complete block.
+pub enum TagRemainder {
+    /// The remainder of an `ID3v2` tag, and the language of its comment.
+    Id3v2 {
+        /// Frames that weren't split off
+        remainder: <Id3v2Tag as SplitTag>::Remainder,
+        /// Language of the first comment without a description
+        comment_language: Option<[u8; 3]>,
+    },
+    /// The remainder of an MP4 ilst.
+    Ilst(<Ilst as SplitTag>::Remainder),
+    /// A block that a generic `Tag` holds completely.
+    Generic,
+}
+
+impl TagRemainder {
+    /// Describes every frame or atom in the remainder as "key: value".
+    pub fn describe(&self) -> Vec<String> {
+        match self {
+            TagRemainder::Id3v2 { remainder, .. } => {
+                let tag: &Id3v2Tag = remainder;
+
+                tag.into_iter().map(describe_frame).collect()
+            }
+            TagRemainder::Ilst(remainder) => {
+                let ilst: &Ilst = remainder;
+
+                ilst.into_iter()
+                    .map(|atom| {
+                        let key = match atom.ident() {
+                            AtomIdent::Fourcc(fourcc) => {
+                                fourcc.iter().map(|b| char::from(*b)).collect()
+                            }
+                            AtomIdent::Freeform { mean, name } => {
+                                format!("----:{mean}:{name}")
+                            }
+                        };
+
+                        let value = atom
+                            .data()
+                            .map(describe_atom_data)
+                            .collect::<Vec<_>>()
+                            .join(", ");
+
+                        format!("{key}: {value}")
+                    })
+                    .collect()
+            }
+            TagRemainder::Generic => Vec::new(),
+        }
+    }
+
+    /// Merges `tag` into the remainder and saves the block to the file at
+    /// `path`. A block that ends up empty is removed from the file.
+    pub fn save(self, tag: Tag, path: &Path) -> Result<()> {
+        let tag_type = tag.tag_type();
+
+        let is_empty = match self {
+            TagRemainder::Id3v2 {
+                remainder,
+                comment_language,
+            } => {
+                let mut merged = remainder.merge_tag(tag);
+
+                if let Some(language) = comment_language {
+                    restore_comment_language(&mut merged, language)?;
+                }
+
+                merged.save_to_path(path)?;
+                merged.is_empty()
+            }
+            TagRemainder::Ilst(remainder) => {
+                let merged = remainder.merge_tag(tag);
+
+                merged.save_to_path(path)?;
+                merged.is_empty()
+            }
+            TagRemainder::Generic => {
+                tag.save_to_path(path)?;
+                tag.is_empty()
+            }
+        };
+
+        if is_empty {
+            tag_type.remove_from_path(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the block of `tag_type` from the file at `path` and splits it into
+/// a generic `Tag` and the remainder. A missing block gives an empty `Tag`.
+pub fn read_split_tag(
+    path: &Path,
+    tag_type: TagType,
+) -> Result<(TagRemainder, Tag)> {
+    let file_type = Probe::open(path)?.guess_file_type()?.file_type();
+
+    let mut file = File::open(path)?;
+    let options = ParseOptions::new().read_properties(false);
+
+    let split = match (tag_type, file_type) {
+        (TagType::Id3v2, Some(FileType::Mpeg)) => {
+            split_id3v2(MpegFile::read_from(&mut file, options)?.remove_id3v2())
+        }
+        (TagType::Id3v2, Some(FileType::Aac)) => {
+            split_id3v2(AacFile::read_from(&mut file, options)?.remove_id3v2())
+        }
+        (TagType::Id3v2, Some(FileType::Aiff)) => {
+            split_id3v2(AiffFile::read_from(&mut file, options)?.remove_id3v2())
+        }
+        (TagType::Id3v2, Some(FileType::Wav)) => {
+            split_id3v2(WavFile::read_from(&mut file, options)?.remove_id3v2())
+        }
+        (TagType::Mp4Ilst, Some(FileType::Mp4)) => {
+            let (remainder, tag) = Mp4File::read_from(&mut file, options)?
+                .remove_ilst()
+                .unwrap_or_default()
+                .split_tag();
+
+            (TagRemainder::Ilst(remainder), tag)
+        }
+        _ => {
+            let tagged_file = Probe::open(path)?.guess_file_type()?.read()?;
+
+            let tag = tagged_file
+                .tag(tag_type)
+                .cloned()
+                .unwrap_or_else(|| Tag::new(tag_type));
+
+            (TagRemainder::Generic, tag)
+        }
+    };
+
+    Ok(split)
+}
+
+fn split_id3v2(tag: Option<Id3v2Tag>) -> (TagRemainder, Tag) {
+    let tag = tag.unwrap_or_default();
+
+    let comment_language = tag
+        .comments()
+        .find(|comment| comment.description.is_empty())
+        .map(|comment| comment.language);
+
+    let (remainder, tag) = tag.split_tag();
+
+    (
+        TagRemainder::Id3v2 {
+            remainder,
+            comment_language,
+        },
+        tag,
+    )
+}
+
+/// Merging writes comments without a description in an unknown language,
+/// so the language they had is set again.
+fn restore_comment_language(
+    tag: &mut Id3v2Tag,
+    language: [u8; 3],
+) -> Result<()> {
+    let mut comments = Vec::new();
+
+    tag.retain(|frame| match frame.content() {
+        FrameValue::Comment(comment) if comment.description.is_empty() => {
+            comments.push(comment.clone());
+            false
+        }
+        _ => true,
+    });
+
+    for mut comment in comments {
+        comment.language = language;
+
+        tag.insert(Frame::new(
+            "COMM",
+            FrameValue::Comment(comment),
+            FrameFlags::default(),
+        )?);
+    }
+
+    Ok(())
+}
+
+fn describe_frame(frame: &Frame<'_>) -> String {
+    let id = frame.id_str();
+
+    match frame.content() {
+        FrameValue::Comment(comment) => {
+            format!("{id}:{}: {}", comment.description, comment.content)
+        }
+        FrameValue::UnsynchronizedText(text) => {
+            format!("{id}:{}: {}", text.description, text.content)
+        }
+        FrameValue::UserText(text) => {
+            format!("{id}:{}: {}", text.description, text.content)
+        }
+        FrameValue::UserUrl(url) => {
+            format!("{id}:{}: {}", url.description, url.content)
+        }
+        FrameValue::Text(text) => format!("{id}: {}", text.value),
+        FrameValue::Url(url) => format!("{id}: {}", url.url()),
+        FrameValue::UniqueFileIdentifier(identifier) => format!(
+            "{id}:{}: <{} bytes>",
+            identifier.owner,
+            identifier.identifier.len()
+        ),
+        FrameValue::Binary(binary) => format!("{id}: <{} bytes>", binary.len()),
+        _ => format!("{id}: <frame>"),
+    }
+}
+
+fn describe_atom_data(data: &AtomData) -> String {
+    match data {
+        AtomData::UTF8(text) | AtomData::UTF16(text) => text.clone(),
+        AtomData::SignedInteger(number) => number.to_string(),
+        AtomData::UnsignedInteger(number) => number.to_string(),
+        AtomData::Bool(flag) => flag.to_string(),
+        AtomData::Picture(_) => "<picture>".to_owned(),
+        AtomData::Unknown { data, .. } => format!("<{} bytes>", data.len()),
+    }
+}
+
+/// Writes `tag_values` to the file at `path`, replacing all existing values
+/// of their keys. Parts of the blocks that `TagValue`s can't express are
+/// kept.
+pub(crate) fn write_tag_values(
+    path: &Path,
+    tag_values: &[TagValue],
+) -> Result<()> {
+    let mut tag_types: Vec<TagType> = Vec::new();
+
+    for tag_value in tag_values {
+        let tag_type = tag_value.tag_type()?;
+
+        if !tag_types.contains(&tag_type) {
+            tag_types.push(tag_type);
+        }
+    }
+
+    for tag_type in tag_types {
+        let (remainder, mut tag) = read_split_tag(path, tag_type)?;
+
+        for tag_value in tag_values {
+            if tag_value.tag_type()? != tag_type {
+                continue;
+            }
+
+            let key = ItemKey::from_key(tag_type, tag_value.key());
+
+            tag.remove_key(&key);
+
+            for value in tag_value.values() {
+                tag.push_unchecked(TagItem::new(
+                    key.clone(),
+                    ItemValue::Text(value.clone()),
+                ));
+            }
+        }
+
+        remainder.save(tag, path)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the front cover in `tags`, or the first picture if there is no
+/// front cover. The result doesn't depend on the order of `tags`.
+pub fn front_cover(tags: &[Tag]) -> Option<&Picture> {
+    let mut tags: Vec<&Tag> = tags.iter().collect();
+
+    tags.sort_by_key(|tag| {
+        PICTURE_TAG_TYPES.iter().position(|t| *t == tag.tag_type())
+    });
+
+    let pictures: Vec<&Picture> =
+        tags.into_iter().flat_map(Tag::pictures).collect();
+
+    pictures
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())
+        .copied()
+}
+
+/// Writes the front cover of the audio file at `source` to a new file at
+/// `target`.
+pub(crate) fn extract_cover(source: &Path, target: &Path) -> Result<()> {
+    let tagged_file = Probe::open(source)?.guess_file_type()?.read()?;
+
+    let picture = front_cover(tagged_file.tags())
+        .ok_or_else(|| HistoryError::MissingCover(source.to_owned()))?;
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+
+    file.write_all(picture.data())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use assert_fs::TempDir;
+    use lofty::id3::v2::{CommentFrame, ExtendedTextFrame};
+    use lofty::{Accessor, TextEncoding};
+    use std::fs;
+
+    fn read_id3v2(path: &Path) -> Result<Id3v2Tag> {
+        let mut file = File::open(path)?;
+
+        Ok(MpegFile::read_from(&mut file, ParseOptions::new())?
+            .remove_id3v2()
+            .unwrap())
+    }
+
+    fn comment(description: &str, content: &str) -> Result<Frame<'static>> {
+        Ok(Frame::new(
+            "COMM",
+            FrameValue::Comment(CommentFrame {
+                encoding: TextEncoding::UTF8,
+                language: *b"eng",
+                description: description.to_owned(),
+                content: content.to_owned(),
+            }),
+            FrameFlags::default(),
+        )?)
+    }
+
+    #[test]
+    fn test_tag_type_names() {
+        for (name, tag_type) in &TAG_TYPES {
+            assert_eq!(tag_type_from_name(name), Some(*tag_type));
+            assert_eq!(tag_type_name(*tag_type), *name);
+        }
+
+        assert_eq!(tag_type_from_name("ID3v2"), Some(TagType::Id3v2));
+        assert_eq!(tag_type_from_name("id3v3"), None);
+    }
+
+    #[test]
+    fn test_write_keeps_other_frames() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("track.mp3");
+
+        fs::copy(
+            "../tests/testdata/music/Under Siege - Amon Amarth.mp3",
+            &path,
+        )?;
+
+        let mut tag = Id3v2Tag::new();
+        tag.set_title("Title".to_owned());
+        tag.insert(comment("", "Plain")?);
+        tag.insert(comment("Notes", "Described")?);
+        tag.insert(Frame::new(
+            "TXXX",
+            FrameValue::UserText(ExtendedTextFrame {
+                encoding: TextEncoding::UTF8,
+                description: "Foo".to_owned(),
+                content: "Bar".to_owned(),
+            }),
+            FrameFlags::default(),
+        )?);
+        tag.save_to_path(&path)?;
+
+        write_tag_values(
+            &path,
+            &[TagValue::new(
+                TagType::Id3v2,
+                "TIT2",
+                vec!["New".to_owned()],
+            )],
+        )?;
+
+        let tag = read_id3v2(&path)?;
+
+        assert_eq!(tag.title().as_deref(), Some("New"));
+
+        let comments: Vec<_> = (&tag)
+            .into_iter()
+            .filter_map(|frame| match frame.content() {
+                FrameValue::Comment(c) => Some((
+                    c.language,
+                    c.description.as_str(),
+                    c.content.as_str(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        assert!(comments.contains(&(*b"eng", "", "Plain")));
+        assert!(comments.contains(&(*b"eng", "Notes", "Described")));
+
+        assert!(tag.into_iter().any(|frame| matches!(
+            frame.content(),
+            FrameValue::UserText(text)
+                if text.description == "Foo" && text.content == "Bar"
+        )));
+
+        Ok(())
+    }
+}
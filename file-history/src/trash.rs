@@ -0,0 +1,268 @@
+//! Implements the home trash of the freedesktop.org trash specification.
+//!
+//! <https://specifications.freedesktop.org/trash-spec/trashspec-latest.html>
+
+use crate::{HistoryError, Result};
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const FILES_DIRECTORY: &str = "files";
+const INFO_DIRECTORY: &str = "info";
+const INFO_EXTENSION: &str = "trashinfo";
+
+/// Returns `$XDG_DATA_HOME/Trash`, or `~/.local/share/Trash` if
+/// `$XDG_DATA_HOME` is not set.
+pub fn home_trash_directory() -> Result<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".local").join("share"))
+        })
+        .ok_or(HistoryError::NoTrash)?;
+
+    Ok(data_home.join("Trash"))
+}
+
+/// Fails if `path` and `trash_directory` are on different filesystems,
+/// because moving to the trash is a rename. `trash_directory` is compared by
+/// its closest existing ancestor, because it is created when needed.
+pub(crate) fn check_same_device(
+    trash_directory: &Path,
+    path: &Path,
+) -> Result<()> {
+    if same_device(path, trash_directory)? {
+        Ok(())
+    } else {
+        Err(HistoryError::TrashOnOtherDevice(path.to_owned()))
+    }
+}
+
+/// Whether `path` and `other` are on the same filesystem. `other` is compared
+/// by its closest existing ancestor, because it may not exist yet.
+#[cfg(unix)]
+pub(crate) fn same_device(path: &Path, other: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = fs::symlink_metadata(path)?.dev();
+
+    let other_device = other
+        .ancestors()
+        .find_map(|ancestor| fs::metadata(ancestor).ok())
+        .map(|metadata| metadata.dev());
+
+    Ok(other_device == Some(device))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn same_device(_: &Path, _: &Path) -> Result<bool> {
+    Ok(true)
+}
+
+/// Picks a name in `trash_directory` for `path` that is used by neither a
+/// trashed file nor an info file.
+pub(crate) fn trash_name(
+    trash_directory: &Path,
+    path: &Path,
+) -> Result<OsString> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| HistoryError::NoFileName(path.to_owned()))?;
+
+    let mut candidate = name.to_owned();
+    let mut number = 1;
+
+    while trashed_path(trash_directory, &candidate).exists()
+        || info_path(trash_directory, &candidate).exists()
+    {
+        number += 1;
+        let mut numbered = name.to_owned();
+        numbered.push(format!(".{number}"));
+        candidate = numbered;
+    }
+
+    Ok(candidate)
+}
+
+/// Moves `path` to the trash as `name` and writes its info file.
+pub(crate) fn trash(
+    trash_directory: &Path,
+    name: &OsStr,
+    path: &Path,
+) -> Result<()> {
+    fs::create_dir_all(trash_directory.join(FILES_DIRECTORY))?;
+    fs::create_dir_all(trash_directory.join(INFO_DIRECTORY))?;
+
+    let info_path = info_path(trash_directory, name);
+
+    // The spec requires the info file to be created atomically, to reserve
+    // the name.
+    let mut info_file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&info_path)?;
+
+    info_file.write_all(trash_info(path).as_bytes())?;
+
+    if let Err(err) = fs::rename(path, trashed_path(trash_directory, name)) {
+        fs::remove_file(&info_path)?;
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+/// Moves `name` from the trash back to `path` and removes its info file.
+pub(crate) fn restore(
+    trash_directory: &Path,
+    name: &OsStr,
+    path: &Path,
+) -> Result<()> {
+    fs::rename(trashed_path(trash_directory, name), path)?;
+    fs::remove_file(info_path(trash_directory, name))?;
+
+    Ok(())
+}
+
+pub(crate) fn trashed_path(trash_directory: &Path, name: &OsStr) -> PathBuf {
+    trash_directory.join(FILES_DIRECTORY).join(name)
+}
+
+fn info_path(trash_directory: &Path, name: &OsStr) -> PathBuf {
+    let mut info_name = name.to_owned();
+    info_name.push(format!(".{INFO_EXTENSION}"));
+
+    trash_directory.join(INFO_DIRECTORY).join(info_name)
+}
+
+fn trash_info(path: &Path) -> String {
+    let deletion_date = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S");
+
+    format!(
+        "[Trash Info]\nPath={}\nDeletionDate={deletion_date}\n",
+        escape_path(path)
+    )
+}
+
+/// Escapes `path` like the path component of a URL, as the spec requires.
+fn escape_path(path: &Path) -> String {
+    let mut escaped = String::new();
+
+    for &byte in path_bytes(path).iter() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            escaped.push(byte as char);
+        } else {
+            // Writing to a String never fails.
+            write!(escaped, "%{byte:02X}").unwrap();
+        }
+    }
+
+    escaped
+}
+
+/// The raw bytes of `path`, which need not be valid UTF-8.
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+        Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+    use predicates::prelude::*;
+
+    #[test]
+    fn test_escape_path() {
+        assert_eq!(
+            escape_path(Path::new("/music/Motörhead/Ace of Spades.mp3")),
+            "/music/Mot%C3%B6rhead/Ace%20of%20Spades.mp3"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_escape_non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        // "Motörhead" in Latin-1.
+        let path = Path::new(OsStr::from_bytes(b"/music/Mot\xF6rhead"));
+
+        assert_eq!(escape_path(path), "/music/Mot%F6rhead");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_check_same_device() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.child("cover.jpg");
+
+        path.touch()?;
+
+        check_same_device(&dir.child("Trash"), &path)?;
+
+        // procfs is never on the same filesystem as a temporary directory.
+        assert!(matches!(
+            check_same_device(Path::new("/proc/Trash"), &path),
+            Err(HistoryError::TrashOnOtherDevice(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_name() -> Result<()> {
+        let dir = TempDir::new()?;
+        let trash_directory = dir.child("Trash");
+
+        trash_directory.child("files").child("cover.jpg").touch()?;
+        trash_directory
+            .child("info")
+            .child("cover.jpg.2.trashinfo")
+            .touch()?;
+
+        assert_eq!(
+            trash_name(&trash_directory, Path::new("/music/cover.jpg"))?,
+            "cover.jpg.3"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash_info() -> Result<()> {
+        let dir = TempDir::new()?;
+        let trash_directory = dir.child("Trash");
+        let path = dir.child("cover.jpg");
+
+        path.touch()?;
+
+        trash(&trash_directory, OsStr::new("cover.jpg"), &path)?;
+
+        let info = trash_directory.child("info").child("cover.jpg.trashinfo");
+
+        info.assert(predicate::str::starts_with(format!(
+            "[Trash Info]\nPath={}\nDeletionDate=",
+            escape_path(&path)
+        )));
+
+        Ok(())
+    }
+}
@@ -137,3 +137,28 @@ fn test_read_write_from_disk() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_collect_garbage() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.child("test.file");
+    path.write_str("contents")?;
+
+    let mut history = History::load(dir.path(), FILE_NAME)?;
+
+    let action = Action::remove_file(&path, history.stash_directory())?;
+
+    history.apply(action)?;
+    history.save()?;
+
+    // Referenced: the stash entry is kept
+    assert!(history.collect_garbage()?.is_empty());
+
+    history.clear()?;
+
+    // Dropped: the stash entry is removed
+    assert_eq!(history.collect_garbage()?.len(), 1);
+    assert_eq!(std::fs::read_dir(history.stash_directory())?.count(), 0);
+
+    Ok(())
+}
@@ -1,9 +1,10 @@
-use clap::{Parser, Subcommand};
+use crate::file::tag_type_from_name;
+use clap::{ArgEnum, Parser, Subcommand};
+use lofty::TagType;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug, PartialEq)]
 #[clap(version, about, long_about = None)]
-
 /// Holds application-wide command line arguments.
 pub struct Args {
     /// Sets a custom config file
@@ -14,6 +15,27 @@ pub struct Args {
     /// Only preview current action.
     preview: bool,
 
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        parse(try_from_str = parse_tag_type)
+    )]
+    /// Order in which tag blocks are read, e.g. "id3v2,ape,id3v1".
+    pub(crate) tag_priority: Vec<TagType>,
+
+    #[clap(long)]
+    /// Split tag values on this separator in tags-all. Can be repeated.
+    pub(crate) split_on: Vec<String>,
+
+    #[clap(long, conflicts_with = "path-regex")]
+    /// Read tags from paths, e.g. "{artist}/{album}/{track} - {title}".
+    /// These override all tag blocks.
+    pub(crate) path_pattern: Option<String>,
+
+    #[clap(long)]
+    /// Like --path-pattern, but with a regex with named groups.
+    pub(crate) path_regex: Option<String>,
+
     #[clap(subcommand)]
     pub(crate) command: Command,
 }
@@ -21,6 +43,21 @@ pub struct Args {
 #[derive(Subcommand, Debug, PartialEq)]
 /// Holds per-subcommand command line arguments.
 pub enum Command {
+    /// Trims whitespace, repairs mojibake and removes empty tags.
+    #[clap(name = "clean-tags")]
+    CleanTags {
+        #[clap(short, long)]
+        /// Only preview current action.
+        preview: bool,
+
+        #[clap(short, long, default_value_t=Args::DEFAULT_RECURSION_DEPTH)]
+        /// Maximum recursion depth when gathering files.
+        recurse: usize,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read.
+        fail_fast: bool,
+    },
     /// Clears the history
     #[clap(name = "clear")]
     ClearHistory {
@@ -28,9 +65,93 @@ pub enum Command {
         /// Only preview current action.
         preview: bool,
     },
+    /// Merges tag blocks into a single target block.
+    #[clap(name = "convert-tags")]
+    ConvertTags {
+        #[clap(short, long)]
+        /// Only report the conversion.
+        preview: bool,
+
+        #[clap(short, long, default_value_t=Args::DEFAULT_RECURSION_DEPTH)]
+        /// Maximum recursion depth when gathering files.
+        recurse: usize,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read or converted.
+        fail_fast: bool,
+
+        #[clap(long, parse(try_from_str = parse_tag_type))]
+        /// Block to convert to. Defaults to the primary block of the format.
+        target: Option<TagType>,
+
+        #[clap(
+            long,
+            use_value_delimiter = true,
+            parse(try_from_str = parse_tag_type)
+        )]
+        /// Blocks to leave alone, e.g. "ape,id3v1". Others are removed.
+        keep: Vec<TagType>,
+    },
+    /// Writes the embedded cover of each album to its folder.
+    #[clap(name = "extract-covers")]
+    ExtractCovers {
+        #[clap(short, long)]
+        /// Only preview current action.
+        preview: bool,
+
+        #[clap(short, long, default_value_t=Args::DEFAULT_RECURSION_DEPTH)]
+        /// Maximum recursion depth when gathering files.
+        recurse: usize,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read.
+        fail_fast: bool,
+
+        #[clap(short, long, default_value = "cover")]
+        /// Name of the image, without extension, e.g. "folder".
+        name: String,
+    },
+    /// Manages the history.
+    History {
+        #[clap(subcommand)]
+        command: HistoryCommand,
+    },
     /// Lists all scripts.
     #[clap(name = "list")]
     ListScripts,
+    /// Lists all enabled audio formats.
+    #[clap(name = "list-formats")]
+    ListFormats,
+    /// Shows the merged tags of files and which block they came from.
+    Inspect {
+        #[clap(required = true, parse(from_os_str))]
+        /// Audio files to inspect.
+        paths: Vec<PathBuf>,
+    },
+    /// Writes playlists of files grouped by a script.
+    Playlist {
+        #[clap(short, long)]
+        /// Only preview current action.
+        preview: bool,
+
+        #[clap(short, long, default_value_t=Args::DEFAULT_RECURSION_DEPTH)]
+        /// Maximum recursion depth when gathering files.
+        recurse: usize,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read or interpreted.
+        fail_fast: bool,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// Directory to write playlists to. Defaults to the current directory.
+        output: Option<PathBuf>,
+
+        /// Name of script.
+        name: String,
+
+        /// Arguments of script.
+        arguments: Vec<String>,
+    },
     /// Undo {times} times.
     Undo {
         #[clap(short, long)]
@@ -61,12 +182,96 @@ pub enum Command {
         /// Maximum recursion depth when gathering files.
         recurse: usize,
 
+        #[clap(short, long)]
+        /// Keep the original extension instead of the format's canonical one.
+        keep_extension: bool,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// Directory to read files from. Can be repeated. Defaults to the
+        /// current directory.
+        input: Vec<PathBuf>,
+
+        #[clap(short, long, parse(from_os_str))]
+        /// Directory to write files to. Defaults to the current directory.
+        output: Option<PathBuf>,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read or interpreted.
+        fail_fast: bool,
+
+        #[clap(long)]
+        /// Only move audio files, not their lyrics, cue sheets, covers, etc.
+        no_sidecars: bool,
+
+        #[clap(long, parse(from_os_str))]
+        /// Rewrite the playlists in this directory that point to moved files.
+        /// Can be repeated.
+        playlists: Vec<PathBuf>,
+
+        #[clap(short, long, arg_enum, default_value = "move")]
+        /// Move the files, or copy or link them and leave the originals.
+        mode: RenameMode,
+
+        #[clap(long)]
+        /// Remove leftover images and junk files without asking.
+        remove_leftovers: bool,
+
+        #[clap(long)]
+        /// Move removed leftovers to the trash instead of the stash.
+        trash: bool,
+
+        /// Name of script.
+        name: String,
+
+        /// Arguments of script.
+        arguments: Vec<String>,
+    },
+    /// Update tags according to a script.
+    Retag {
+        #[clap(short, long)]
+        /// Only preview current action.
+        preview: bool,
+
+        #[clap(short, long, default_value_t=Args::DEFAULT_RECURSION_DEPTH)]
+        /// Maximum recursion depth when gathering files.
+        recurse: usize,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read or interpreted.
+        fail_fast: bool,
+
         /// Name of script.
         name: String,
 
         /// Arguments of script.
         arguments: Vec<String>,
     },
+    /// Write tags that are read from the paths of files.
+    #[clap(name = "tags-from-path")]
+    TagsFromPath {
+        #[clap(short, long)]
+        /// Only preview current action.
+        preview: bool,
+
+        #[clap(short, long, default_value_t=Args::DEFAULT_RECURSION_DEPTH)]
+        /// Maximum recursion depth when gathering files.
+        recurse: usize,
+
+        #[clap(long)]
+        /// Abort on the first file that can't be read or doesn't match.
+        fail_fast: bool,
+
+        #[clap(long)]
+        /// Replace existing values instead of only filling in empty tags.
+        overwrite: bool,
+
+        #[clap(long)]
+        /// Treat the pattern as a regex with named groups.
+        regex: bool,
+
+        /// Pattern, e.g. "{artist}/{album}/{track} - {title}".
+        pattern: String,
+    },
     /// Adds examples to the filesystem.
     Seed {
         #[clap(short, long)]
@@ -79,6 +284,30 @@ pub enum Command {
     },
 }
 
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+/// How renamed files end up at their target.
+pub enum RenameMode {
+    /// Move the file.
+    Move,
+    /// Copy the file.
+    Copy,
+    /// Create a hard link to the file. Both must be on the same filesystem.
+    Hardlink,
+    /// Create a symbolic link to the file.
+    Symlink,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+/// Holds arguments for history subcommands.
+pub enum HistoryCommand {
+    /// Removes stashed files that no longer belong to any history entry.
+    Gc {
+        #[clap(short, long)]
+        /// Only preview current action.
+        preview: bool,
+    },
+}
+
 impl Args {
     pub(crate) const DEFAULT_PREVIEW_AMOUNT: usize = 8;
     pub(crate) const DEFAULT_RECURSION_DEPTH: usize = 4;
@@ -89,29 +318,57 @@ impl Args {
         let preview_aggregate = preview_override
             || self.preview
             || match self.command {
-                Command::ClearHistory { preview, .. }
+                Command::CleanTags { preview, .. }
+                | Command::ClearHistory { preview, .. }
+                | Command::ConvertTags { preview, .. }
+                | Command::ExtractCovers { preview, .. }
+                | Command::History {
+                    command: HistoryCommand::Gc { preview },
+                }
+                | Command::Playlist { preview, .. }
                 | Command::Undo { preview, .. }
                 | Command::Redo { preview, .. }
                 | Command::Rename { preview, .. }
-                | Command::Seed { preview, .. } => preview,
-                Command::ListScripts => false,
+                | Command::Retag { preview, .. }
+                | Command::Seed { preview, .. }
+                | Command::TagsFromPath { preview, .. } => preview,
+                Command::ListScripts
+                | Command::ListFormats
+                | Command::Inspect { .. } => false,
             };
 
         self.preview = preview_aggregate;
 
         match &mut self.command {
-            Command::ClearHistory { preview, .. }
+            Command::CleanTags { preview, .. }
+            | Command::ClearHistory { preview, .. }
+            | Command::ConvertTags { preview, .. }
+            | Command::ExtractCovers { preview, .. }
+            | Command::History {
+                command: HistoryCommand::Gc { preview },
+            }
+            | Command::Playlist { preview, .. }
             | Command::Undo { preview, .. }
             | Command::Redo { preview, .. }
             | Command::Rename { preview, .. }
-            | Command::Seed { preview, .. } => *preview = preview_aggregate,
-            Command::ListScripts => (),
-        };
+            | Command::Retag { preview, .. }
+            | Command::Seed { preview, .. }
+            | Command::TagsFromPath { preview, .. } => {
+                *preview = preview_aggregate;
+            }
+            Command::ListScripts
+            | Command::ListFormats
+            | Command::Inspect { .. } => (),
+        }
 
         self
     }
 }
 
+fn parse_tag_type(s: &str) -> Result<TagType, String> {
+    tag_type_from_name(s).ok_or_else(|| format!("Unknown tag type \"{s}\""))
+}
+
 /// Parses arguments
 pub(crate) fn parse_args(preview_override: bool) -> Args {
     Args::parse().aggregate_preview(preview_override)
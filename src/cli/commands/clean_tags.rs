@@ -0,0 +1,283 @@
+use super::files::{gather_files, print_skipped_files, SkippedFile};
+use super::tag_changes::{print_changes_preview, write_tags, TagChanges};
+use crate::cli::{ui, Config};
+use crate::file::{id3v2_major_version, AudioFile};
+use anyhow::Result;
+use file_history::{History, TagValue};
+use indicatif::ProgressIterator;
+use lofty::{ItemKey, ItemValue, Tag, TagType};
+
+/// Characters CP1252 puts in 0x80-0x9F, where Latin-1 has control codes.
+const CP1252_EXTENSIONS: [(char, u8); 27] = [
+    ('€', 0x80),
+    ('‚', 0x82),
+    ('ƒ', 0x83),
+    ('„', 0x84),
+    ('…', 0x85),
+    ('†', 0x86),
+    ('‡', 0x87),
+    ('ˆ', 0x88),
+    ('‰', 0x89),
+    ('Š', 0x8A),
+    ('‹', 0x8B),
+    ('Œ', 0x8C),
+    ('Ž', 0x8E),
+    ('‘', 0x91),
+    ('’', 0x92),
+    ('“', 0x93),
+    ('”', 0x94),
+    ('•', 0x95),
+    ('–', 0x96),
+    ('—', 0x97),
+    ('˜', 0x98),
+    ('™', 0x99),
+    ('š', 0x9A),
+    ('›', 0x9B),
+    ('œ', 0x9C),
+    ('ž', 0x9E),
+    ('Ÿ', 0x9F),
+];
+
+pub(crate) fn clean_tags(
+    preview: bool,
+    config: &Config,
+    recursion_depth: usize,
+    fail_fast: bool,
+) -> Result<()> {
+    let mut history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    let mut skipped = Vec::new();
+
+    let files = gather_files(
+        recursion_depth,
+        config.tag_options(),
+        fail_fast,
+        &mut skipped,
+    )?;
+
+    let changes = clean_files(files, fail_fast, &mut skipped)?;
+
+    let result = if changes.is_empty() {
+        println!("All tags are already clean.");
+        Ok(())
+    } else {
+        print_changes_preview(
+            &changes,
+            crate::cli::Args::DEFAULT_PREVIEW_AMOUNT,
+        );
+
+        write_tags(preview, &mut history, changes)
+    };
+
+    print_skipped_files(&skipped);
+
+    result
+}
+
+fn clean_files(
+    files: Vec<AudioFile>,
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<Vec<TagChanges>> {
+    let bar = ui::create_progressbar(
+        files.len() as u64,
+        "Checking tags...",
+        "Checked tags.",
+        false,
+    );
+
+    let mut changes = Vec::new();
+
+    for audiofile in files.into_iter().progress_with(bar) {
+        let path = audiofile.path().to_owned();
+
+        match changes_from_file(&audiofile) {
+            Ok(Some(tag_changes)) => changes.push(tag_changes),
+            Ok(None) => (),
+            Err(error) if !fail_fast => {
+                skipped.push(SkippedFile { path, error });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(changes)
+}
+
+fn changes_from_file(audiofile: &AudioFile) -> Result<Option<TagChanges>> {
+    let mut previous = Vec::new();
+    let mut current = Vec::new();
+
+    for tag in audiofile.tag_blocks() {
+        let repair_mojibake = match tag.tag_type() {
+            TagType::Id3v1 => true,
+            TagType::Id3v2 => id3v2_major_version(audiofile.path())? == Some(3),
+            _ => false,
+        };
+
+        // ID3v1 stores Latin-1, so other characters can't be written back.
+        let latin1_only = tag.tag_type() == TagType::Id3v1;
+
+        for key in text_keys(tag) {
+            let Some(native_key) = key.map_key(tag.tag_type(), true) else {
+                continue;
+            };
+
+            let previous_values: Vec<String> =
+                tag.get_strings(&key).map(str::to_owned).collect();
+
+            let current_values: Vec<String> = previous_values
+                .iter()
+                .map(|value| {
+                    clean_value(&key, value, repair_mojibake, latin1_only)
+                })
+                .filter(|value| !value.is_empty())
+                .collect();
+
+            if previous_values != current_values {
+                previous.push(TagValue::new(
+                    tag.tag_type(),
+                    native_key,
+                    previous_values,
+                ));
+                current.push(TagValue::new(
+                    tag.tag_type(),
+                    native_key,
+                    current_values,
+                ));
+            }
+        }
+    }
+
+    if current.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(TagChanges {
+        path: audiofile.path().to_owned(),
+        previous,
+        current,
+    }))
+}
+
+/// Returns every key in `tag` with a text value, once.
+fn text_keys(tag: &Tag) -> Vec<ItemKey> {
+    let mut keys: Vec<ItemKey> = Vec::new();
+
+    for item in tag.items() {
+        if matches!(item.value(), ItemValue::Text(_))
+            && !keys.contains(item.key())
+        {
+            keys.push(item.key().clone());
+        }
+    }
+
+    keys
+}
+
+fn clean_value(
+    key: &ItemKey,
+    value: &str,
+    repair_mojibake: bool,
+    latin1_only: bool,
+) -> String {
+    let value = repair_mojibake
+        .then(|| repair_latin1_mojibake(value))
+        .flatten()
+        .filter(|repaired| {
+            !latin1_only || repaired.chars().all(|c| u32::from(c) <= 0xFF)
+        })
+        .unwrap_or_else(|| value.to_owned());
+
+    // Line breaks in comments and lyrics are intentional.
+    if matches!(key, ItemKey::Comment | ItemKey::Lyrics) {
+        value.trim().to_owned()
+    } else {
+        value.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+}
+
+/// Reverses UTF-8 text that was decoded as Latin-1 or CP1252, e.g.
+/// "`BeyoncÃ©`". Returns `None` if `value` doesn't look like mojibake.
+fn repair_latin1_mojibake(value: &str) -> Option<String> {
+    if value.is_ascii() {
+        return None;
+    }
+
+    let bytes = value
+        .chars()
+        .map(latin1_byte)
+        .collect::<Option<Vec<u8>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
+
+fn latin1_byte(char: char) -> Option<u8> {
+    u8::try_from(u32::from(char)).ok().or_else(|| {
+        CP1252_EXTENSIONS
+            .iter()
+            .find(|(c, _)| *c == char)
+            .map(|(_, byte)| *byte)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_value() {
+        assert_eq!(
+            clean_value(&ItemKey::TrackTitle, "  Some   Title\t", false, false),
+            "Some Title"
+        );
+        assert_eq!(
+            clean_value(
+                &ItemKey::Comment,
+                " First line\nSecond line ",
+                false,
+                false
+            ),
+            "First line\nSecond line"
+        );
+        assert_eq!(clean_value(&ItemKey::TrackTitle, "   ", false, false), "");
+    }
+
+    #[test]
+    fn test_clean_latin1_value() {
+        assert_eq!(
+            clean_value(&ItemKey::TrackArtist, "BeyoncÃ©", true, true),
+            "Beyoncé"
+        );
+
+        // The en dash can't be stored as Latin-1.
+        let value = "Sigur RÃ³s â€\u{201c} HoppÃ\u{ad}polla";
+
+        assert_eq!(clean_value(&ItemKey::TrackTitle, value, true, true), value);
+        assert_eq!(
+            clean_value(&ItemKey::TrackTitle, value, true, false),
+            "Sigur Rós – Hoppípolla"
+        );
+    }
+
+    #[test]
+    fn test_repair_latin1_mojibake() {
+        assert_eq!(
+            repair_latin1_mojibake("BeyoncÃ©"),
+            Some("Beyoncé".to_owned())
+        );
+        // CP1252
+        assert_eq!(
+            repair_latin1_mojibake("Sigur RÃ³s â€\u{201c} HoppÃ\u{ad}polla"),
+            Some("Sigur Rós – Hoppípolla".to_owned())
+        );
+        // Latin-1
+        assert_eq!(
+            repair_latin1_mojibake("Sigur RÃ³s â\u{80}\u{93} HoppÃ\u{ad}polla"),
+            Some("Sigur Rós – Hoppípolla".to_owned())
+        );
+        assert_eq!(repair_latin1_mojibake("Beyoncé"), None);
+        assert_eq!(repair_latin1_mojibake("RÃ³s and Hoppípolla"), None);
+        assert_eq!(repair_latin1_mojibake("Plain ASCII"), None);
+    }
+}
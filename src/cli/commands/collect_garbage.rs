@@ -0,0 +1,18 @@
+use crate::cli::Config;
+use anyhow::Result;
+use file_history::History;
+
+pub(crate) fn collect_garbage(preview: bool, config: &Config) -> Result<()> {
+    let history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    let garbage = if preview {
+        history.stash_garbage()?
+    } else {
+        history.collect_garbage()?
+    };
+
+    let pp = if preview { Config::PREVIEW_PREFIX } else { "" };
+    println!("{pp}Removed {} stashed files.", garbage.len());
+
+    Ok(())
+}
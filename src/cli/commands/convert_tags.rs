@@ -0,0 +1,554 @@
+use super::files::{gather_files, print_skipped_files, SkippedFile};
+use crate::cli::{ui, Config};
+use crate::file::{id3v2_major_version, tag_type_name, AudioFile};
+use anyhow::{bail, Result};
+use file_history::{read_split_tag, Action, History, TagRemainder};
+use indicatif::ProgressIterator;
+use lofty::{ItemKey, ItemValue, Tag, TagItem, TagType};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Tag blocks that can hold pictures.
+const PICTURE_TAG_TYPES: [TagType; 4] = [
+    TagType::Ape,
+    TagType::Id3v2,
+    TagType::Mp4Ilst,
+    TagType::VorbisComments,
+];
+
+/// The new layout of the tags of a single file.
+struct Conversion {
+    path: PathBuf,
+    sources: Vec<TagType>,
+    target: Tag,
+    remainder: TagRemainder,
+    lost: Vec<String>,
+}
+
+pub(crate) fn convert_tags(
+    preview: bool,
+    config: &Config,
+    recursion_depth: usize,
+    fail_fast: bool,
+    target: Option<TagType>,
+    keep: &[TagType],
+) -> Result<()> {
+    let mut history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    let mut skipped = Vec::new();
+
+    let files = gather_files(
+        recursion_depth,
+        config.tag_options(),
+        fail_fast,
+        &mut skipped,
+    )?;
+
+    let conversions =
+        plan_conversions(files, target, keep, fail_fast, &mut skipped)?;
+
+    let result = if conversions.is_empty() {
+        println!("All tags are already in the target layout.");
+        Ok(())
+    } else {
+        print!("{}", report(&conversions));
+
+        write_conversions(preview, &mut history, conversions)
+    };
+
+    print_skipped_files(&skipped);
+
+    result
+}
+
+fn plan_conversions(
+    files: Vec<AudioFile>,
+    target: Option<TagType>,
+    keep: &[TagType],
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<Vec<Conversion>> {
+    let bar = ui::create_progressbar(
+        files.len() as u64,
+        "Checking tags...",
+        "Checked tags.",
+        false,
+    );
+
+    let mut conversions = Vec::new();
+
+    for audiofile in files.into_iter().progress_with(bar) {
+        let path = audiofile.path().to_owned();
+
+        match plan_conversion(&audiofile, target, keep) {
+            Ok(Some(conversion)) => conversions.push(conversion),
+            Ok(None) => (),
+            Err(error) if !fail_fast => {
+                skipped.push(SkippedFile { path, error });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(conversions)
+}
+
+/// Merges every block that isn't kept into the target block. Values from
+/// blocks with a higher priority win. Returns `None` if the file already has
+/// the target layout.
+fn plan_conversion(
+    audiofile: &AudioFile,
+    target: Option<TagType>,
+    keep: &[TagType],
+) -> Result<Option<Conversion>> {
+    let target_type = target.unwrap_or_else(|| audiofile.primary_tag_type());
+
+    if !audiofile
+        .format()
+        .file_type()
+        .supports_tag_type(target_type)
+    {
+        bail!(
+            "{} does not support {} tags.",
+            audiofile.format().name(),
+            tag_type_name(target_type)
+        )
+    }
+
+    let sources: Vec<TagType> = audiofile
+        .tag_blocks()
+        .iter()
+        .map(Tag::tag_type)
+        .filter(|tag_type| *tag_type == target_type || !keep.contains(tag_type))
+        .collect();
+
+    let is_outdated_id3v2 = target_type == TagType::Id3v2
+        && id3v2_major_version(audiofile.path())?.is_some_and(|v| v < 4);
+
+    if sources.iter().all(|tag_type| *tag_type == target_type)
+        && !is_outdated_id3v2
+    {
+        return Ok(None);
+    }
+
+    // The blocks are read again in full, including the parts that a
+    // generic tag can't hold.
+    let blocks = sources
+        .into_iter()
+        .map(|tag_type| read_split_tag(audiofile.path(), tag_type))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(Some(convert_blocks(audiofile.path(), blocks, target_type)))
+}
+
+/// Merges `blocks` into a single block of `target_type`. The parts of the
+/// target block that a generic tag can't hold are kept, those of the other
+/// blocks are lost.
+fn convert_blocks(
+    path: &Path,
+    blocks: Vec<(TagRemainder, Tag)>,
+    target_type: TagType,
+) -> Conversion {
+    let (remainders, sources): (Vec<TagRemainder>, Vec<Tag>) =
+        blocks.into_iter().unzip();
+
+    let mut target = Tag::new(target_type);
+    let mut lost = Vec::new();
+    let mut seen_keys: Vec<ItemKey> = Vec::new();
+
+    for source in &sources {
+        let mut source_keys = Vec::new();
+
+        for item in source.items() {
+            if seen_keys.contains(item.key()) {
+                if !target.items().any(|kept| {
+                    kept.key() == item.key() && kept.value() == item.value()
+                }) {
+                    lost.push(format!(
+                        "{} (overridden)",
+                        describe_item(item, source.tag_type())
+                    ));
+                }
+
+                continue;
+            }
+
+            if survives_conversion(item, source.tag_type(), target_type) {
+                target.push_unchecked(item.clone());
+
+                if !source_keys.contains(item.key()) {
+                    source_keys.push(item.key().clone());
+                }
+            } else {
+                lost.push(describe_item(item, source.tag_type()));
+            }
+        }
+
+        seen_keys.extend(source_keys);
+    }
+
+    // Only the pictures of the first block that has any are kept.
+    let mut picture_sources =
+        sources.iter().filter(|tag| !tag.pictures().is_empty());
+
+    if let Some(source) = picture_sources.next() {
+        if PICTURE_TAG_TYPES.contains(&target_type) {
+            for picture in source.pictures() {
+                target.push_picture(picture.clone());
+            }
+        } else {
+            lost.push(describe_pictures(source));
+        }
+    }
+
+    lost.extend(picture_sources.map(describe_pictures));
+
+    let mut remainder = TagRemainder::Generic;
+
+    for (source, source_remainder) in sources.iter().zip(remainders) {
+        if source.tag_type() == target_type {
+            remainder = source_remainder;
+        } else {
+            lost.extend(source_remainder.describe().into_iter().map(
+                |description| {
+                    format!(
+                        "{description} [{}]",
+                        tag_type_name(source.tag_type())
+                    )
+                },
+            ));
+        }
+    }
+
+    Conversion {
+        path: path.to_owned(),
+        sources: sources.iter().map(Tag::tag_type).collect(),
+        target,
+        remainder,
+        lost,
+    }
+}
+
+/// Items of the target block always survive. Items from other blocks
+/// survive if the target block has a key for them and they are text. Parts
+/// of a block that aren't items are handled by `convert_blocks`.
+fn survives_conversion(
+    item: &TagItem,
+    source_type: TagType,
+    target_type: TagType,
+) -> bool {
+    if source_type == target_type {
+        return true;
+    }
+
+    item.key().map_key(target_type, false).is_some()
+        && !matches!(item.value(), ItemValue::Binary(_))
+}
+
+fn describe_item(item: &TagItem, tag_type: TagType) -> String {
+    let key = item
+        .key()
+        .map_key(tag_type, true)
+        .map_or_else(|| format!("{:?}", item.key()), str::to_owned);
+
+    let value = match item.value() {
+        ItemValue::Text(text) | ItemValue::Locator(text) => text.clone(),
+        ItemValue::Binary(binary) => format!("<{} bytes>", binary.len()),
+    };
+
+    format!("{}: {} [{}]", key, value, tag_type_name(tag_type))
+}
+
+fn describe_pictures(tag: &Tag) -> String {
+    format!(
+        "{} picture(s) [{}]",
+        tag.pictures().len(),
+        tag_type_name(tag.tag_type())
+    )
+}
+
+fn report(conversions: &[Conversion]) -> String {
+    let mut report = format!("\nConverting {} files:\n", conversions.len());
+
+    for conversion in conversions {
+        let sources: Vec<&str> = conversion
+            .sources
+            .iter()
+            .map(|tag_type| tag_type_name(*tag_type))
+            .collect();
+
+        // Writing to a String can't fail.
+        let _ = writeln!(
+            report,
+            "{}: {} -> {}",
+            conversion.path.display(),
+            sources.join(" + "),
+            tag_type_name(conversion.target.tag_type())
+        );
+
+        for description in &conversion.lost {
+            let _ = writeln!(report, "  Would not survive: {description}");
+        }
+    }
+
+    report.push('\n');
+
+    report
+}
+
+fn write_conversions(
+    preview: bool,
+    history: &mut History,
+    conversions: Vec<Conversion>,
+) -> Result<()> {
+    let bar = ui::create_progressbar(
+        conversions.len() as u64,
+        "Converting tags...",
+        "Converted tags.",
+        preview,
+    );
+
+    for conversion in conversions.into_iter().progress_with(bar) {
+        if preview {
+            continue;
+        }
+
+        let path = conversion.path.clone();
+        let converted = convert_copy(conversion, history.stash_directory())?;
+
+        history.apply(Action::convert_tags(path, converted))?;
+    }
+
+    if !preview {
+        history.save()?;
+    }
+
+    Ok(())
+}
+
+/// Writes the new layout to a uniquely named copy of the file in
+/// `directory`. The target block is saved before the other blocks are
+/// removed, so the values are never only in memory.
+fn convert_copy(conversion: Conversion, directory: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    let copy = NamedTempFile::new_in(directory)?;
+
+    fs::copy(&conversion.path, copy.path())?;
+
+    let target_type = conversion.target.tag_type();
+
+    conversion.remainder.save(conversion.target, copy.path())?;
+
+    for tag_type in &conversion.sources {
+        if *tag_type != target_type {
+            tag_type.remove_from_path(copy.path())?;
+        }
+    }
+
+    Ok(copy.into_temp_path().keep()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::TagOptions;
+    use crate::tags::Tags;
+    use assert_fs::TempDir;
+    use lofty::id3::v2::{
+        CommentFrame, ExtendedTextFrame, Frame, FrameFlags, FrameValue,
+        Id3v2Tag,
+    };
+    use lofty::mpeg::MpegFile;
+    use lofty::{
+        Accessor, AudioFile as _, MimeType, ParseOptions, Picture, PictureType,
+        TagExt, TextEncoding,
+    };
+
+    fn read(path: &Path) -> Result<AudioFile> {
+        AudioFile::new(path, &TagOptions::new(Vec::new(), Vec::new(), None))
+    }
+
+    #[test]
+    fn test_report() {
+        let mut target = Tag::new(TagType::Id3v2);
+        target.insert_text(ItemKey::TrackTitle, "Title".to_owned());
+
+        let conversion = Conversion {
+            path: PathBuf::from("track.mp3"),
+            sources: vec![TagType::Id3v2, TagType::Ape, TagType::Id3v1],
+            target,
+            remainder: TagRemainder::Generic,
+            lost: vec!["1 picture(s) [ape]".to_owned()],
+        };
+
+        assert_eq!(
+            report(&[conversion]),
+            "\nConverting 1 files:\n\
+             track.mp3: id3v2 + ape + id3v1 -> id3v2\n  \
+             Would not survive: 1 picture(s) [ape]\n\n"
+        );
+    }
+
+    #[test]
+    fn test_lost_pictures() {
+        let picture = Picture::new_unchecked(
+            PictureType::CoverFront,
+            MimeType::Png,
+            None,
+            b"\x89PNG\r\n\x1a\n".to_vec(),
+        );
+
+        let mut id3v2 = Tag::new(TagType::Id3v2);
+        id3v2.push_picture(picture.clone());
+
+        let mut ape = Tag::new(TagType::Ape);
+        ape.push_picture(picture.clone());
+        ape.push_picture(picture);
+
+        let blocks =
+            vec![(TagRemainder::Generic, id3v2), (TagRemainder::Generic, ape)];
+
+        // Only the pictures of the ID3v2 block are kept.
+        let conversion =
+            convert_blocks(Path::new("track.mp3"), blocks, TagType::Ape);
+
+        assert_eq!(conversion.target.pictures().len(), 1);
+        assert_eq!(conversion.lost, ["2 picture(s) [ape]"]);
+    }
+
+    #[test]
+    fn test_convert_and_undo() -> Result<()> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("track.mp3");
+
+        // This file has ID3v2.3 and ID3v1 tags.
+        fs::copy(
+            "tests/testdata/music/SET MIDI=SYNTH1 MAPG MODE1 - MASTER BOOT RECORD.mp3",
+            &path,
+        )?;
+
+        let mut ape = Tag::new(TagType::Ape);
+        assert!(ape.insert_text(ItemKey::Label, "Label".to_owned()));
+        ape.save_to_path(&path)?;
+
+        let original = fs::read(&path)?;
+        let title = read(&path)?.title().map(str::to_owned);
+
+        assert_eq!(id3v2_major_version(&path)?, Some(3));
+
+        let conversion =
+            plan_conversion(&read(&path)?, Some(TagType::Id3v2), &[])?.unwrap();
+
+        assert_eq!(
+            conversion.sources,
+            [TagType::Id3v2, TagType::Ape, TagType::Id3v1]
+        );
+
+        let mut history = History::load(tempdir.path(), "history")?;
+
+        write_conversions(false, &mut history, vec![conversion])?;
+
+        // Converted: a single ID3v2.4 block with the values of all blocks
+        let converted = read(&path)?;
+
+        assert_eq!(id3v2_major_version(&path)?, Some(4));
+        assert_eq!(
+            converted
+                .tag_blocks()
+                .iter()
+                .map(Tag::tag_type)
+                .collect::<Vec<_>>(),
+            [TagType::Id3v2]
+        );
+        assert_eq!(converted.title().map(str::to_owned), title);
+        assert_eq!(converted.label(), Some("Label"));
+
+        history.undo(1)?;
+
+        // Undone: the file is restored exactly
+        assert_eq!(fs::read(&path)?, original);
+
+        history.redo(1)?;
+
+        assert_eq!(id3v2_major_version(&path)?, Some(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_described_frames() -> Result<()> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("track.mp3");
+
+        fs::copy("tests/testdata/music/Under Siege - Amon Amarth.mp3", &path)?;
+
+        let mut id3v2 = Id3v2Tag::new();
+        id3v2.set_title("Title".to_owned());
+        id3v2.insert(Frame::new(
+            "COMM",
+            FrameValue::Comment(CommentFrame {
+                encoding: TextEncoding::UTF8,
+                language: *b"eng",
+                description: "Notes".to_owned(),
+                content: "Described".to_owned(),
+            }),
+            FrameFlags::default(),
+        )?);
+        id3v2.insert(Frame::new(
+            "TXXX",
+            FrameValue::UserText(ExtendedTextFrame {
+                encoding: TextEncoding::UTF8,
+                description: "Foo".to_owned(),
+                content: "Bar".to_owned(),
+            }),
+            FrameFlags::default(),
+        )?);
+        id3v2.save_to_path(&path)?;
+
+        let mut ape = Tag::new(TagType::Ape);
+        assert!(ape.insert_text(ItemKey::Label, "Label".to_owned()));
+        ape.save_to_path(&path)?;
+
+        // APE can't hold either frame.
+        let conversion =
+            plan_conversion(&read(&path)?, Some(TagType::Ape), &[])?.unwrap();
+
+        assert!(conversion
+            .lost
+            .contains(&"COMM:Notes: Described [id3v2]".to_owned()));
+        assert!(conversion.lost.contains(&"Foo: Bar [id3v2]".to_owned()));
+
+        // ID3v2 keeps both. The file also has an ID3v1 tag.
+        let conversion = plan_conversion(
+            &read(&path)?,
+            Some(TagType::Id3v2),
+            &[TagType::Id3v1],
+        )?
+        .unwrap();
+
+        assert!(conversion.lost.is_empty());
+
+        let mut history = History::load(tempdir.path(), "history")?;
+
+        write_conversions(false, &mut history, vec![conversion])?;
+
+        let id3v2 = MpegFile::read_from(
+            &mut fs::File::open(&path)?,
+            ParseOptions::new(),
+        )?
+        .remove_id3v2()
+        .unwrap();
+
+        assert!(id3v2.into_iter().any(|frame| matches!(
+            frame.content(),
+            FrameValue::Comment(comment) if comment.description == "Notes"
+        )));
+        assert_eq!(read(&path)?.get_raw("TXXX:Foo"), Some("Bar"));
+        assert_eq!(read(&path)?.label(), Some("Label"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use super::files::{gather_files, print_skipped_files};
+use crate::cli::{ui, Config};
+use crate::file::AudioFile;
+use anyhow::Result;
+use file_history::{front_cover, Action, History};
+use indicatif::ProgressIterator;
+use lofty::MimeType;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub(crate) fn extract_covers(
+    preview: bool,
+    config: &Config,
+    recursion_depth: usize,
+    fail_fast: bool,
+    name: &str,
+) -> Result<()> {
+    let mut history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    let mut skipped = Vec::new();
+
+    let files = gather_files(
+        recursion_depth,
+        config.tag_options(),
+        fail_fast,
+        &mut skipped,
+    )?;
+
+    let actions = plan_covers(files, name);
+
+    let result = if actions.is_empty() {
+        println!("There are no covers to extract.");
+        Ok(())
+    } else {
+        print_covers_preview(&actions);
+
+        write_covers(preview, &mut history, actions)
+    };
+
+    print_skipped_files(&skipped);
+
+    result
+}
+
+/// Picks the first file with a cover in every directory that doesn't have a
+/// folder image called `name` yet.
+fn plan_covers(files: Vec<AudioFile>, name: &str) -> Vec<(PathBuf, PathBuf)> {
+    let mut directories: BTreeMap<PathBuf, Vec<AudioFile>> = BTreeMap::new();
+
+    for audiofile in files {
+        // Audio files always have a parent.
+        let directory = audiofile.path().parent().unwrap().to_owned();
+
+        directories.entry(directory).or_default().push(audiofile);
+    }
+
+    let mut covers = Vec::new();
+
+    for (directory, mut audiofiles) in directories {
+        if has_folder_image(&directory, name) {
+            continue;
+        }
+
+        audiofiles.sort_by(|left, right| left.path().cmp(right.path()));
+
+        let cover = audiofiles.iter().find_map(|audiofile| {
+            front_cover(audiofile.tag_blocks())
+                .map(|picture| (audiofile.path(), picture))
+        });
+
+        if let Some((source, picture)) = cover {
+            let extension = image_extension(picture.mime_type());
+
+            let target = directory.join(format!("{name}.{extension}"));
+
+            covers.push((source.to_owned(), target));
+        }
+    }
+
+    covers
+}
+
+fn has_folder_image(directory: &Path, name: &str) -> bool {
+    std::fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|entry| {
+            let path = entry.path();
+
+            path.is_file()
+                && path.file_stem().is_some_and(|stem| {
+                    stem.to_string_lossy().eq_ignore_ascii_case(name)
+                })
+        })
+}
+
+fn image_extension(mime_type: &MimeType) -> &'static str {
+    match mime_type {
+        MimeType::Png => "png",
+        MimeType::Gif => "gif",
+        MimeType::Bmp => "bmp",
+        MimeType::Tiff => "tif",
+        _ => "jpg",
+    }
+}
+
+fn print_covers_preview(covers: &[(PathBuf, PathBuf)]) {
+    println!("\nExtracting {} covers:", covers.len());
+
+    for (_, target) in covers {
+        println!("{}", target.display());
+    }
+
+    println!();
+}
+
+fn write_covers(
+    preview: bool,
+    history: &mut History,
+    covers: Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
+    let bar = ui::create_progressbar(
+        covers.len() as u64,
+        "Extracting covers...",
+        "Extracted covers.",
+        preview,
+    );
+
+    for (source, target) in covers.into_iter().progress_with(bar) {
+        let action = Action::extract_cover(source, target);
+
+        if !preview {
+            history.apply(action)?;
+        }
+    }
+
+    history.save()?;
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::cli::{ui, Config};
+use crate::file::{AudioFile, TagOptions};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// A file that could not be processed, and why.
+pub(crate) struct SkippedFile {
+    pub(crate) path: PathBuf,
+    pub(crate) error: anyhow::Error,
+}
+
+/// Gathers audio files from the current directory.
+pub(crate) fn gather_files(
+    recursion_depth: usize,
+    tag_options: &TagOptions,
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<Vec<AudioFile>> {
+    gather_files_in(
+        &[std::env::current_dir()?],
+        recursion_depth,
+        tag_options,
+        fail_fast,
+        skipped,
+    )
+}
+
+/// Gathers audio files from every directory in `roots`.
+pub(crate) fn gather_files_in(
+    roots: &[PathBuf],
+    recursion_depth: usize,
+    tag_options: &TagOptions,
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<Vec<AudioFile>> {
+    let spinner = ui::AudioFileSpinner::new(
+        "audio files",
+        "total files",
+        "Gathering files...",
+    );
+
+    let mut paths = Vec::new();
+
+    for root in roots {
+        paths.extend(Config::search_path(
+            root,
+            recursion_depth,
+            &|p| AudioFile::is_audio(p),
+            Some(&spinner),
+        ));
+    }
+
+    spinner.finish("Gathered files.");
+
+    let mut files = Vec::new();
+
+    for path in paths {
+        match AudioFile::new(&path, tag_options) {
+            Ok(audiofile) => files.push(audiofile),
+            Err(error) if !fail_fast => {
+                skipped.push(SkippedFile { path, error });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(files)
+}
+
+pub(crate) fn print_skipped_files(skipped: &[SkippedFile]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    let length = skipped.len();
+
+    println!(
+        "Skipped {} file{}:",
+        length,
+        if length > 1 { "s" } else { "" }
+    );
+
+    for skipped_file in skipped {
+        println!("{}: {}", skipped_file.path.display(), skipped_file.error);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::cli::Config;
+use crate::file::{tag_type_name, AudioFile};
+use anyhow::Result;
+use lofty::ItemValue;
+use std::path::PathBuf;
+
+pub(crate) fn inspect(config: &Config, paths: &[PathBuf]) -> Result<()> {
+    for path in paths {
+        let audiofile = AudioFile::new(path, config.tag_options())?;
+
+        println!("{} ({})", path.display(), audiofile.format().name());
+
+        for (item, tag_type) in audiofile.merged_items() {
+            let value = match item.value() {
+                ItemValue::Text(text) | ItemValue::Locator(text) => {
+                    text.clone()
+                }
+                ItemValue::Binary(binary) => {
+                    format!("<{} bytes>", binary.len())
+                }
+            };
+
+            println!(
+                "  {:?}: {} [{}]",
+                item.key(),
+                value,
+                tag_type_name(tag_type)
+            );
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
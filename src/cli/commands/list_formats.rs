@@ -0,0 +1,9 @@
+use crate::file::FORMATS;
+
+pub(crate) fn list_formats() {
+    println!("Enabled formats:");
+
+    for format in &FORMATS {
+        println!("{}: {}", format.name(), format.extensions().join(", "));
+    }
+}
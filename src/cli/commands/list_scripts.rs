@@ -22,5 +22,12 @@ pub(crate) fn list_scripts(config: &Config) -> Result<()> {
 }
 
 fn print_script_info(script: &Script) {
-    print!("{}[{}]", script.name(), script.parameters().join(" "));
+    for (entry_point, parameters) in script.entry_points() {
+        println!(
+            "{} {}[{}]",
+            script.name(),
+            entry_point,
+            parameters.join(" ")
+        );
+    }
 }
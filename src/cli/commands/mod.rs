@@ -1,11 +1,31 @@
+mod clean_tags;
 mod clear_history;
+mod collect_garbage;
+mod convert_tags;
+mod extract_covers;
+mod files;
+mod inspect;
+mod list_formats;
 mod list_scripts;
+mod playlist;
 mod rename;
+mod retag;
 mod seed;
+mod tag_changes;
+mod tags_from_path;
 mod undo;
 
+pub(crate) use clean_tags::clean_tags;
 pub(crate) use clear_history::clear_history;
+pub(crate) use collect_garbage::collect_garbage;
+pub(crate) use convert_tags::convert_tags;
+pub(crate) use extract_covers::extract_covers;
+pub(crate) use inspect::inspect;
+pub(crate) use list_formats::list_formats;
 pub(crate) use list_scripts::list_scripts;
-pub(crate) use rename::rename;
+pub(crate) use playlist::playlist;
+pub(crate) use rename::{rename, RenameOptions};
+pub(crate) use retag::retag;
 pub(crate) use seed::seed;
+pub(crate) use tags_from_path::tags_from_path;
 pub(crate) use undo::{undo, UndoMode};
@@ -0,0 +1,219 @@
+use super::files::{gather_files, print_skipped_files, SkippedFile};
+use super::rename::{create_dir, normalize, relative_path};
+use crate::cli::{ui, Config};
+use crate::file::AudioFile;
+use crate::script::Script;
+use crate::tapr::run_script;
+use anyhow::{anyhow, bail, Result};
+use file_history::{Action, History};
+use indicatif::ProgressIterator;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tapr::Value;
+
+const PLAYLIST_HEADER: &str = "#EXTM3U";
+
+pub(crate) fn playlist(
+    preview: bool,
+    config: &Config,
+    recursion_depth: usize,
+    fail_fast: bool,
+    output: Option<&Path>,
+    name: &str,
+    arguments: &[String],
+) -> Result<()> {
+    let mut history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    let mut script = config.get_script(name)?;
+
+    let mut skipped = Vec::new();
+
+    let files = gather_files(
+        recursion_depth,
+        config.tag_options(),
+        fail_fast,
+        &mut skipped,
+    )?;
+
+    script.add_arguments_to_node(Script::PLAYLIST, arguments)?;
+
+    let current_dir = std::env::current_dir()?;
+
+    let directory = match output {
+        Some(output) => normalize(&current_dir.join(output)),
+        None => current_dir,
+    };
+
+    let playlists = interpret_files(&script, files, fail_fast, &mut skipped)?;
+
+    let result = if playlists.is_empty() {
+        println!("There are no playlists to write.");
+        Ok(())
+    } else {
+        print_playlists_preview(&playlists);
+
+        write_playlists(preview, &mut history, &directory, playlists)
+    };
+
+    print_skipped_files(&skipped);
+
+    result
+}
+
+/// Groups the paths of `files` by the playlist name the script returns.
+fn interpret_files(
+    script: &Script,
+    files: Vec<AudioFile>,
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<BTreeMap<String, Vec<PathBuf>>> {
+    let bar = ui::create_progressbar(
+        files.len() as u64,
+        "Interpreting files...",
+        "Interpreted files.",
+        false,
+    );
+
+    let mut playlists: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+    for audiofile in files.into_iter().progress_with(bar) {
+        let path = audiofile.path().to_owned();
+
+        match playlist_from_file(script, audiofile) {
+            Ok(Some(name)) => playlists.entry(name).or_default().push(path),
+            Ok(None) => (),
+            Err(error) if !fail_fast => {
+                skipped.push(SkippedFile { path, error });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    for paths in playlists.values_mut() {
+        paths.sort();
+    }
+
+    Ok(playlists)
+}
+
+/// Runs the script for `audiofile`. Returns `None` if the file doesn't
+/// belong in any playlist.
+fn playlist_from_file(
+    script: &Script,
+    audiofile: AudioFile,
+) -> Result<Option<String>> {
+    let value = run_script(script, Arc::new(audiofile))?;
+
+    let name = match value {
+        Value::Nil => return Ok(None),
+        Value::String(string) => string,
+        Value::Number(number) => number.to_string(),
+        _ => bail!("Script did not return a playlist name."),
+    };
+
+    validate_name(&name)?;
+
+    Ok(Some(name))
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        Err(anyhow!("Playlist name is empty."))
+    } else if name.contains(['/', '\\']) {
+        Err(anyhow!(
+            "Playlist name \"{name}\" contains a path separator."
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Lists `paths` relative to `directory`, after an extended M3U header.
+fn playlist_contents(directory: &Path, paths: &[PathBuf]) -> String {
+    let mut contents = format!("{PLAYLIST_HEADER}\n");
+
+    for path in paths {
+        contents.push_str(&relative_path(directory, path).to_string_lossy());
+        contents.push('\n');
+    }
+
+    contents
+}
+
+fn print_playlists_preview(playlists: &BTreeMap<String, Vec<PathBuf>>) {
+    println!("\nWriting {} playlists:", playlists.len());
+
+    for (name, paths) in playlists {
+        println!("{name}.m3u8: {} files", paths.len());
+    }
+
+    println!();
+}
+
+fn write_playlists(
+    preview: bool,
+    history: &mut History,
+    directory: &Path,
+    playlists: BTreeMap<String, Vec<PathBuf>>,
+) -> Result<()> {
+    create_dir(preview, history, directory)?;
+
+    let bar = ui::create_progressbar(
+        playlists.len() as u64,
+        "Writing playlists...",
+        "Wrote playlists.",
+        preview,
+    );
+
+    for (name, paths) in playlists.into_iter().progress_with(bar) {
+        let path = directory.join(format!("{name}.m3u8"));
+
+        let current = playlist_contents(directory, &paths);
+
+        // Existing playlists are replaced, so undo can restore them.
+        let previous =
+            if path.exists() { Some(fs::read_to_string(&path)?) } else { None };
+
+        if previous.as_ref() == Some(&current) {
+            continue;
+        }
+
+        let action = Action::write_file(path, previous, current);
+
+        if !preview {
+            history.apply(action)?;
+        }
+    }
+
+    history.save()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playlist_contents() {
+        let paths = [
+            PathBuf::from("/music/Artist/Album/01 - Title.mp3"),
+            PathBuf::from("/music/Other/02 - Title.flac"),
+        ];
+
+        let contents = playlist_contents(Path::new("/music/playlists"), &paths);
+
+        let lines: Vec<&str> = contents.lines().collect();
+
+        assert_eq!(lines[0], PLAYLIST_HEADER);
+        assert_eq!(
+            lines[1..].iter().map(PathBuf::from).collect::<Vec<_>>(),
+            [
+                PathBuf::from("../Artist/Album/01 - Title.mp3"),
+                PathBuf::from("../Other/02 - Title.flac"),
+            ]
+        );
+    }
+}
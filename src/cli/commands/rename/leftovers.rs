@@ -0,0 +1,311 @@
+use super::{remove_dir, RenameOptions};
+use crate::cli::ui;
+use anyhow::Result;
+use file_history::{home_trash_directory, Action, History};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const IMAGE_EXTENSIONS: [&str; 8] =
+    ["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+
+const SYSTEM_FILES: [&str; 3] = ["thumbs.db", ".ds_store", "desktop.ini"];
+
+/// Kinds of files that are commonly left behind after the audio files in a
+/// directory have been moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LeftoverKind {
+    Image,
+    Thumbnail,
+    SystemFile,
+    EmptyNfo,
+}
+
+impl LeftoverKind {
+    /// Classifies `path`, or returns `None` if it isn't a known leftover.
+    pub(crate) fn classify(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        if SYSTEM_FILES.contains(&name.as_str()) || name.starts_with("._") {
+            Some(LeftoverKind::SystemFile)
+        } else if extension.as_deref() == Some("nfo") {
+            fs::metadata(path)
+                .is_ok_and(|metadata| metadata.len() == 0)
+                .then_some(LeftoverKind::EmptyNfo)
+        } else if extension
+            .as_deref()
+            .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension))
+        {
+            // Windows Media Player stores album art as "AlbumArtSmall.jpg"
+            // and "AlbumArt_{GUID}_Large.jpg".
+            if name.starts_with("albumart") {
+                Some(LeftoverKind::Thumbnail)
+            } else {
+                Some(LeftoverKind::Image)
+            }
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for LeftoverKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            LeftoverKind::Image => "image",
+            LeftoverKind::Thumbnail => "thumbnail",
+            LeftoverKind::SystemFile => "system file",
+            LeftoverKind::EmptyNfo => "empty .nfo",
+        };
+
+        write!(f, "{description}")
+    }
+}
+
+/// What is left in the source directories that could not be removed.
+#[derive(Debug, Default)]
+pub(crate) struct Leftovers {
+    /// Leftover files in directories that only contain leftovers.
+    files: Vec<(PathBuf, LeftoverKind)>,
+    /// Directories that can be removed once `files` are gone, deepest first.
+    directories: Vec<PathBuf>,
+    /// Directories that contain other files.
+    kept: Vec<PathBuf>,
+}
+
+impl Leftovers {
+    /// Classifies the contents of `directories`, which must be ordered
+    /// deepest first. Entries in `moved` count as gone, so leftovers can be
+    /// listed before anything is moved.
+    pub(crate) fn find(
+        directories: &[PathBuf],
+        moved: &HashSet<&Path>,
+    ) -> Self {
+        let mut leftovers = Leftovers::default();
+        let mut removable: HashSet<&Path> = HashSet::new();
+
+        for directory in directories.iter().filter(|path| path.is_dir()) {
+            let mut files = Vec::new();
+            let mut is_removable = true;
+
+            for path in fs::read_dir(directory)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+            {
+                if moved.contains(path.as_path()) {
+                    continue;
+                }
+
+                if path.is_dir() {
+                    is_removable &= removable.contains(path.as_path());
+                } else if let Some(kind) = LeftoverKind::classify(&path) {
+                    files.push((path, kind));
+                } else {
+                    is_removable = false;
+                }
+            }
+
+            if is_removable {
+                files.sort_by(|left, right| left.0.cmp(&right.0));
+                leftovers.files.extend(files);
+                leftovers.directories.push(directory.clone());
+                removable.insert(directory);
+            } else {
+                leftovers.kept.push(directory.clone());
+            }
+        }
+
+        leftovers
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.directories.is_empty() && self.kept.is_empty()
+    }
+
+    pub(crate) fn print(&self) {
+        if !self.directories.is_empty() {
+            println!(
+                "\nFound {} leftover files in {} folders:",
+                self.files.len(),
+                self.directories.len()
+            );
+
+            for (path, kind) in &self.files {
+                println!("{kind}: {}", path.display());
+            }
+        }
+
+        if !self.kept.is_empty() {
+            println!(
+                "\n{} folders contain other files and were kept:",
+                self.kept.len()
+            );
+
+            for directory in &self.kept {
+                println!("{}", directory.display());
+            }
+        }
+
+        println!();
+    }
+
+    /// Removes the leftover files and the directories that are empty
+    /// afterwards. The files are stashed, so their removal can be undone.
+    pub(crate) fn remove(self, history: &mut History) -> Result<()> {
+        for (path, _) in self.files {
+            let action = Action::remove_file(path, history.stash_directory())?;
+
+            history.apply(action)?;
+        }
+
+        for directory in self.directories {
+            remove_dir(history, Action::rmdir(directory))?;
+        }
+
+        println!("Removed leftover files.");
+
+        Ok(())
+    }
+
+    /// Moves the outermost directories that only contain leftovers to the
+    /// trash, along with their contents. Nothing is moved if any of them is
+    /// on another filesystem than the trash.
+    pub(crate) fn trash(self, history: &mut History) -> Result<()> {
+        let trash_directory = home_trash_directory()?;
+
+        let mut actions = Vec::new();
+
+        for directory in &self.directories {
+            let is_outermost = directory.parent().map_or(true, |parent| {
+                !self.directories.iter().any(|other| other == parent)
+            });
+
+            if is_outermost {
+                actions.push(Action::trash(directory, &trash_directory)?);
+            }
+        }
+
+        for action in actions {
+            history.apply(action)?;
+        }
+
+        println!("Moved leftover files to the trash.");
+
+        Ok(())
+    }
+}
+
+/// Lists the leftovers in `directories` once the files in `moved` are gone,
+/// and removes or trashes them if the options say so or the user agrees.
+/// Nothing is removed in preview mode.
+pub(crate) fn handle_leftovers(
+    preview: bool,
+    history: &mut History,
+    directories: &[PathBuf],
+    moved: &HashSet<&Path>,
+    options: &RenameOptions,
+) -> Result<()> {
+    let leftovers = Leftovers::find(directories, moved);
+
+    if leftovers.is_empty() {
+        return Ok(());
+    }
+
+    leftovers.print();
+
+    if preview || leftovers.files.is_empty() {
+        return Ok(());
+    }
+
+    if !options.remove_leftovers && !ui::confirm("Remove leftover files?")? {
+        println!("Use --remove-leftovers to remove them.");
+    } else if options.trash {
+        leftovers.trash(history)?;
+    } else {
+        leftovers.remove(history)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    #[test]
+    fn test_classify() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        tempdir.child("empty.nfo").touch()?;
+        tempdir.child("full.nfo").write_str("Release notes")?;
+
+        let cases = [
+            ("cover.JPG", Some(LeftoverKind::Image)),
+            ("AlbumArtSmall.jpg", Some(LeftoverKind::Thumbnail)),
+            ("Thumbs.db", Some(LeftoverKind::SystemFile)),
+            (".DS_Store", Some(LeftoverKind::SystemFile)),
+            ("empty.nfo", Some(LeftoverKind::EmptyNfo)),
+            ("full.nfo", None),
+            ("booklet.pdf", None),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(
+                LeftoverKind::classify(tempdir.child(name).path()),
+                expected,
+                "{name}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_leftovers() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let album = tempdir.child("album");
+        let scans = album.child("scans");
+        let other = tempdir.child("other");
+
+        album.child("folder.jpg").touch()?;
+        let track = album.child("01.mp3");
+        track.touch()?;
+        scans.child("front.png").touch()?;
+        other.child("Thumbs.db").touch()?;
+        other.child("notes.txt").touch()?;
+
+        let directories = [
+            scans.to_path_buf(),
+            album.to_path_buf(),
+            other.to_path_buf(),
+        ];
+
+        // The track is about to be moved.
+        let moved = HashSet::from([track.path()]);
+
+        let leftovers = Leftovers::find(&directories, &moved);
+
+        assert_eq!(
+            leftovers.files,
+            [
+                (scans.child("front.png").to_path_buf(), LeftoverKind::Image),
+                (album.child("folder.jpg").to_path_buf(), LeftoverKind::Image),
+            ]
+        );
+        assert_eq!(leftovers.directories, [scans.path(), album.path()]);
+        assert_eq!(leftovers.kept, [other.path()]);
+
+        Ok(())
+    }
+}
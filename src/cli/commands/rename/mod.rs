@@ -1,24 +1,59 @@
+mod leftovers;
+mod playlists;
+mod sidecars;
 mod validate;
 
+use super::files::{gather_files_in, print_skipped_files, SkippedFile};
+use crate::cli::args::RenameMode;
 use crate::cli::{ui, Config};
 use crate::file::AudioFile;
 use crate::script::Script;
-use crate::tapr::get_tapr_environment;
+use crate::tapr::run_script;
 use anyhow::{anyhow, bail, Result};
 use file_history::{Action, History, HistoryError};
 use indicatif::ProgressIterator;
-use std::fs;
+use leftovers::handle_leftovers;
+use playlists::playlist_actions;
+pub(crate) use playlists::{normalize, relative_path};
+use sidecars::sidecar_actions;
 use std::path::{Path, PathBuf};
-use tapr::{Interpreter, Value};
+use std::sync::Arc;
+use tapr::Value;
 use validate::validate_actions;
 
 pub(crate) const FORBIDDEN_CHARACTERS: [char; 10] =
     ['<', '>', ':', '\'', '|', '?', '*', '~', '/', '\\'];
 
+/// Options that control how files are renamed.
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct RenameOptions {
+    /// Directories to read files from. Defaults to the current directory.
+    pub(crate) inputs: Vec<PathBuf>,
+    /// Directory that targets are relative to. Defaults to the current
+    /// directory.
+    pub(crate) output: Option<PathBuf>,
+    /// Maximum recursion depth when gathering files.
+    pub(crate) recursion_depth: usize,
+    /// Keep the original extension instead of the format's canonical one.
+    pub(crate) keep_extension: bool,
+    /// Abort on the first file that can't be read or interpreted.
+    pub(crate) fail_fast: bool,
+    /// Move sidecar files along with their audio files.
+    pub(crate) sidecars: bool,
+    /// Directories with playlists to rewrite after moving files.
+    pub(crate) playlists: Vec<PathBuf>,
+    /// Whether files are moved, copied or linked.
+    pub(crate) mode: RenameMode,
+    /// Remove leftover images and junk files without asking.
+    pub(crate) remove_leftovers: bool,
+    /// Move removed leftovers to the trash instead of the stash.
+    pub(crate) trash: bool,
+}
+
 pub(crate) fn rename(
     preview: bool,
     config: &Config,
-    recursion_depth: usize,
+    options: &RenameOptions,
     name: &str,
     arguments: &[String],
 ) -> Result<()> {
@@ -26,65 +61,124 @@ pub(crate) fn rename(
 
     let mut script = config.get_script(name)?;
 
-    let files = gather_files(recursion_depth)?;
+    let inputs = input_roots(&options.inputs)?;
+    let output = output_root(options.output.as_deref())?;
+
+    let mut skipped = Vec::new();
 
-    script.add_arguments_to_node(arguments)?;
+    let files = gather_files_in(
+        &inputs,
+        options.recursion_depth,
+        config.tag_options(),
+        options.fail_fast,
+        &mut skipped,
+    )?;
 
-    let actions = interpret_files(&script, files)?;
+    script.add_arguments_to_node(Script::RENAME, arguments)?;
 
-    if actions.is_empty() {
+    let mut actions =
+        interpret_files(&script, files, options, &output, &mut skipped)?;
+
+    let result = if actions.is_empty() {
         println!("There are no audio files to rename.");
         Ok(())
     } else {
+        if options.sidecars {
+            let sidecars =
+                sidecar_actions(&actions, options.mode, &mut skipped);
+            actions.extend(sidecars);
+        }
+
         let (actions, _filtered_actions) = partition_actions(actions);
 
         validate_actions(&actions)?;
 
-        let common_path = get_common_path(&actions);
+        // Copying or linking leaves the playlists valid.
+        let playlists = if options.mode == RenameMode::Move {
+            let current_dir = std::env::current_dir()?;
+
+            let directories: Vec<PathBuf> = options
+                .playlists
+                .iter()
+                .map(|directory| current_dir.join(directory))
+                .collect();
+
+            playlist_actions(
+                &directories,
+                options.recursion_depth,
+                &actions,
+                &mut skipped,
+            )
+        } else {
+            Vec::new()
+        };
 
         perform_actions(
             preview,
-            recursion_depth,
-            &common_path,
+            options,
+            &inputs,
             &mut history,
             actions,
+            playlists,
         )
-    }
+    };
+
+    print_skipped_files(&skipped);
+
+    result
 }
-fn gather_files(recursion_depth: usize) -> Result<Vec<AudioFile>> {
-    let path = std::env::current_dir()?;
 
-    let spinner = ui::AudioFileSpinner::new(
-        "audio files",
-        "total files",
-        "Gathering files...",
-    );
+/// Canonicalizes `inputs`, or returns the current directory if there are
+/// none. Inputs inside another input are dropped, so no file is read twice.
+fn input_roots(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if inputs.is_empty() {
+        return Ok(vec![std::env::current_dir()?]);
+    }
 
-    let paths = Config::search_path(
-        &path,
-        recursion_depth,
-        &|p| {
-            p.extension().map_or(false, |extension| {
-                for supported_extension in AudioFile::SUPPORTED_EXTENSIONS {
-                    if extension == supported_extension {
-                        return true;
-                    }
-                }
+    let mut roots = Vec::new();
 
-                false
-            })
-        },
-        Some(&spinner),
-    );
+    for input in inputs {
+        if !input.is_dir() {
+            bail!("Input \"{}\" is not a directory.", input.display())
+        }
 
-    spinner.finish("Gathered files.");
+        roots.push(dunce::canonicalize(input)?);
+    }
+
+    // Parents sort before their children.
+    roots.sort();
+
+    let mut disjoint_roots: Vec<PathBuf> = Vec::new();
+
+    for root in roots {
+        if !disjoint_roots.iter().any(|other| root.starts_with(other)) {
+            disjoint_roots.push(root);
+        }
+    }
 
-    paths.iter().map(|p| AudioFile::new(p)).collect()
+    Ok(disjoint_roots)
+}
+
+/// Makes `output` absolute, or returns the current directory if it is
+/// `None`. The output doesn't have to exist yet.
+fn output_root(output: Option<&Path>) -> Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+
+    let output = match output {
+        Some(output) => dunce::canonicalize(output)
+            .unwrap_or_else(|_| current_dir.join(output)),
+        None => current_dir,
+    };
+
+    Ok(output)
 }
 
 fn interpret_files(
     script: &Script,
     files: Vec<AudioFile>,
+    options: &RenameOptions,
+    output: &Path,
+    skipped: &mut Vec<SkippedFile>,
 ) -> Result<Vec<Action>> {
     let bar = ui::create_progressbar(
         files.len() as u64,
@@ -93,73 +187,67 @@ fn interpret_files(
         false,
     );
 
-    let actions: Result<Vec<Action>> = files
-        .into_iter()
-        .progress_with(bar)
-        .map(|audiofile| action_from_file(script, audiofile))
-        .collect();
-
-    actions
-}
+    let mut actions = Vec::new();
 
-fn get_common_path(actions: &[Action]) -> PathBuf {
-    debug_assert!(!actions.is_empty());
+    for audiofile in files.into_iter().progress_with(bar) {
+        let path = audiofile.path().to_owned();
 
-    // We have already returned if no files were found, so this index
-    // should be safe.
-    let (common_path, _) = actions[0].get_src_tgt_unchecked();
-    let mut common_path = common_path.to_path_buf();
-
-    for action in actions {
-        let (path, _) = action.get_src_tgt_unchecked();
-
-        let mut new_common_path = PathBuf::new();
-
-        for (left, right) in path.components().zip(common_path.components()) {
-            if left == right {
-                new_common_path.push(left);
-            } else {
-                break;
+        match action_from_file(script, audiofile, options, output) {
+            Ok(action) => actions.push(action),
+            Err(error) if !options.fail_fast => {
+                skipped.push(SkippedFile { path, error });
             }
+            Err(error) => return Err(error),
         }
-        common_path = new_common_path;
     }
 
-    common_path
+    Ok(actions)
 }
 
-fn action_from_file(script: &Script, audiofile: AudioFile) -> Result<Action> {
+fn action_from_file(
+    script: &Script,
+    audiofile: AudioFile,
+    options: &RenameOptions,
+    output: &Path,
+) -> Result<Action> {
     let source = audiofile.path().to_owned();
 
-    // We already know this is a file with either an "mp3" or "ogg"
-    // extension, so we unwrap safely.
-    debug_assert!(source.extension().is_some());
+    let canonical_extension = audiofile.format().canonical_extension();
 
-    let extension = audiofile.extension().to_owned();
+    let extension = if options.keep_extension {
+        audiofile.extension().unwrap_or(canonical_extension)
+    } else {
+        canonical_extension
+    }
+    .to_owned();
 
-    let mut intp = create_interpreter(audiofile);
-    let string = run_interpreter(script, &mut intp)?;
+    let value = run_script(script, Arc::new(audiofile))?;
+    let string = segments_to_string(value)?;
 
-    let target = create_target_path_from_string(&string, &extension)?;
+    let target = create_target_path_from_string(&string, &extension, output);
 
-    let action = Action::mv(source, target);
+    let action = create_action(options.mode, source, target)?;
 
     Ok(action)
 }
 
-fn create_interpreter(audiofile: AudioFile) -> Interpreter<'static> {
-    let mut intp = Interpreter::default();
-
-    let env = get_tapr_environment(audiofile);
-
-    intp.push_environment(env);
+/// Fails for hard links across filesystems, before anything is renamed.
+fn create_action<P, Q>(mode: RenameMode, source: P, target: Q) -> Result<Action>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let action = match mode {
+        RenameMode::Move => Action::mv(source, target),
+        RenameMode::Copy => Action::copy(source, target),
+        RenameMode::Hardlink => Action::hard_link(source, target)?,
+        RenameMode::Symlink => Action::symlink(source, target),
+    };
 
-    intp
+    Ok(action)
 }
 
-fn run_interpreter(script: &Script, intp: &mut Interpreter) -> Result<String> {
-    let value = script.accept(intp)?;
-
+fn segments_to_string(value: Value) -> Result<String> {
     let Value::List(segments) = value else {
         bail!("Script did not return list of segments.")
     };
@@ -184,14 +272,13 @@ fn run_interpreter(script: &Script, intp: &mut Interpreter) -> Result<String> {
 fn create_target_path_from_string(
     string: &str,
     extension: &str,
-) -> Result<PathBuf> {
+    output: &Path,
+) -> PathBuf {
     let target_path = PathBuf::from(format!("{string}.{extension}"));
 
-    // If target_path has an absolute path, join will clobber the current_dir,
+    // If target_path has an absolute path, join will clobber the output,
     // so this is always safe.
-    let target = std::env::current_dir()?.join(target_path);
-
-    Ok(target)
+    output.join(target_path)
 }
 
 fn replace_invalid_chars(string: String) -> String {
@@ -209,19 +296,36 @@ fn partition_actions(actions: Vec<Action>) -> (Vec<Action>, Vec<Action>) {
 
 fn perform_actions(
     preview: bool,
-    recursion_depth: usize,
-    common_path: &Path,
+    options: &RenameOptions,
+    inputs: &[PathBuf],
     history: &mut History,
     actions: Vec<Action>,
+    playlists: Vec<Action>,
 ) -> Result<()> {
     ui::print_actions_preview(
         &actions,
         crate::cli::Args::DEFAULT_PREVIEW_AMOUNT,
     );
 
-    move_files(preview, history, actions)?;
+    let dirs = source_dirs(inputs, &actions);
+
+    let sources: Vec<PathBuf> = actions
+        .iter()
+        .map(|action| action.get_src_tgt_unchecked().0.to_owned())
+        .collect();
+
+    move_files(preview, history, actions, options.mode)?;
 
-    clean_up_source_dirs(preview, history, common_path, recursion_depth)?;
+    rewrite_playlists(preview, history, playlists)?;
+
+    // Copying or linking leaves the sources alone.
+    if options.mode == RenameMode::Move {
+        clean_up_source_dirs(preview, history, &dirs)?;
+
+        let moved = sources.iter().map(PathBuf::as_path).collect();
+
+        handle_leftovers(preview, history, &dirs, &moved, options)?;
+    }
 
     history.save()?;
 
@@ -232,11 +336,20 @@ fn move_files(
     preview: bool,
     history: &mut History,
     actions: Vec<Action>,
+    mode: RenameMode,
 ) -> Result<()> {
+    let (msg, finished_msg) = match mode {
+        RenameMode::Move => ("Moving files...", "Moved files."),
+        RenameMode::Copy => ("Copying files...", "Copied files."),
+        RenameMode::Hardlink | RenameMode::Symlink => {
+            ("Linking files...", "Linked files.")
+        }
+    };
+
     let bar = ui::create_progressbar(
         actions.len() as u64,
-        "Moving files...",
-        "Moved files.",
+        msg,
+        finished_msg,
         preview,
     );
 
@@ -254,7 +367,35 @@ fn move_files(
     Ok(())
 }
 
-fn create_dir(preview: bool, history: &mut History, path: &Path) -> Result<()> {
+fn rewrite_playlists(
+    preview: bool,
+    history: &mut History,
+    playlists: Vec<Action>,
+) -> Result<()> {
+    if playlists.is_empty() {
+        return Ok(());
+    }
+
+    let length = playlists.len();
+
+    if !preview {
+        for action in playlists {
+            history.apply(action)?;
+        }
+    }
+
+    let pp = if preview { Config::PREVIEW_PREFIX } else { "" };
+
+    println!("{pp}Rewrote {length} playlists.");
+
+    Ok(())
+}
+
+pub(crate) fn create_dir(
+    preview: bool,
+    history: &mut History,
+    path: &Path,
+) -> Result<()> {
     if path.is_dir() {
         return Ok(());
     }
@@ -275,12 +416,9 @@ fn create_dir(preview: bool, history: &mut History, path: &Path) -> Result<()> {
 fn clean_up_source_dirs(
     preview: bool,
     history: &mut History,
-    common_path: &Path,
-    recursion_depth: usize,
+    dirs: &[PathBuf],
 ) -> Result<()> {
-    let dirs = gather_dirs(common_path, recursion_depth);
-
-    let actions: Vec<Action> = dirs.into_iter().map(Action::rmdir).collect();
+    let actions: Vec<Action> = dirs.iter().map(Action::rmdir).collect();
 
     if !preview {
         for action in actions {
@@ -295,21 +433,39 @@ fn clean_up_source_dirs(
     Ok(())
 }
 
-fn gather_dirs(path: &Path, depth: usize) -> Vec<PathBuf> {
-    let mut dirs = Vec::new();
-
-    if depth == 0 {
-        return dirs;
-    }
+/// Returns the directories that contained the sources of `actions` and their
+/// ancestors below the input `roots`, deepest first. The roots themselves and
+/// other directories are left alone.
+fn source_dirs(roots: &[PathBuf], actions: &[Action]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
 
-    for entry in fs::read_dir(path).into_iter().flatten().flatten() {
-        let dir = entry.path();
-        if dir.is_dir() {
-            dirs.extend(gather_dirs(&dir, depth - 1));
-            dirs.push(dir);
+    for action in actions {
+        let (source, _) = action.get_src_tgt_unchecked();
+
+        let Some(root) = roots.iter().find(|root| source.starts_with(root))
+        else {
+            continue;
+        };
+
+        for dir in source
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(root) && dir != root)
+        {
+            dirs.push(dir.to_owned());
         }
     }
 
+    // Children have more components than their parents.
+    dirs.sort_by(|left, right| {
+        right
+            .components()
+            .count()
+            .cmp(&left.components().count())
+            .then_with(|| left.cmp(right))
+    });
+    dirs.dedup();
+
     dirs
 }
 
@@ -350,6 +506,27 @@ mod tests {
     use super::*;
     use anyhow::Result;
     use assert_fs::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_source_dirs() {
+        let roots = [PathBuf::from("/music")];
+
+        let actions = [
+            Action::mv("/music/inbox/Album/01.mp3", "/music/Artist/01.mp3"),
+            Action::mv("/music/inbox/Album/CD2/01.mp3", "/music/Artist/02.mp3"),
+            Action::mv("/music/03.mp3", "/music/Artist/03.mp3"),
+        ];
+
+        assert_eq!(
+            source_dirs(&roots, &actions),
+            [
+                PathBuf::from("/music/inbox/Album/CD2"),
+                PathBuf::from("/music/inbox/Album"),
+                PathBuf::from("/music/inbox"),
+            ]
+        );
+    }
 
     #[test]
     fn test_remove_dir_error_codes() -> Result<()> {
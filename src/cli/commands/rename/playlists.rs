@@ -0,0 +1,236 @@
+use crate::cli::commands::files::SkippedFile;
+use crate::cli::Config;
+use anyhow::{anyhow, Result};
+use file_history::Action;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const PLAYLIST_EXTENSIONS: [&str; 2] = ["m3u", "m3u8"];
+
+/// Finds the playlists in `directories` and rewrites the entries that point
+/// to a source of `actions`. Returns a `WriteFile` action for every playlist
+/// that changed.
+pub(crate) fn playlist_actions(
+    directories: &[PathBuf],
+    recursion_depth: usize,
+    actions: &[Action],
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<Action> {
+    let moves: HashMap<PathBuf, PathBuf> = actions
+        .iter()
+        .map(|action| {
+            let (source, target) = action.get_src_tgt_unchecked();
+            (normalize(source), normalize(target))
+        })
+        .collect();
+
+    let mut playlist_actions = Vec::new();
+
+    for path in find_playlists(directories, recursion_depth) {
+        // Playlists that are moved themselves are left alone.
+        if find_target(&moves, &normalize(&path)).is_some() {
+            continue;
+        }
+
+        match rewrite_playlist(&path, &moves) {
+            Ok(Some((previous, current))) => {
+                playlist_actions.push(Action::write_file(
+                    path,
+                    Some(previous),
+                    current,
+                ));
+            }
+            Ok(None) => (),
+            Err(error) => skipped.push(SkippedFile { path, error }),
+        }
+    }
+
+    playlist_actions
+}
+
+fn find_playlists(
+    directories: &[PathBuf],
+    recursion_depth: usize,
+) -> Vec<PathBuf> {
+    let predicate = |path: &Path| {
+        path.extension().is_some_and(|extension| {
+            PLAYLIST_EXTENSIONS
+                .contains(&extension.to_string_lossy().to_lowercase().as_str())
+        })
+    };
+
+    let mut playlists: Vec<PathBuf> = directories
+        .iter()
+        .flat_map(|directory| {
+            Config::search_path(directory, recursion_depth, &predicate, None)
+        })
+        .collect();
+
+    playlists.sort();
+    playlists.dedup();
+
+    playlists
+}
+
+/// Returns the previous and current contents of the playlist at `path`, or
+/// `None` if none of its entries were moved.
+fn rewrite_playlist(
+    path: &Path,
+    moves: &HashMap<PathBuf, PathBuf>,
+) -> Result<Option<(String, String)>> {
+    let previous = String::from_utf8(fs::read(path)?)
+        .map_err(|_| anyhow!("Playlist is not valid UTF-8."))?;
+
+    // Playlists are files, so they always have a parent.
+    let directory = normalize(path.parent().unwrap());
+
+    let mut current = String::with_capacity(previous.len());
+
+    for line in previous.split_inclusive('\n') {
+        let entry = line.trim_end_matches(['\r', '\n']);
+        let line_ending = &line[entry.len()..];
+
+        match rewrite_entry(entry, &directory, moves) {
+            Some(rewritten) => current.push_str(&rewritten),
+            None => current.push_str(entry),
+        }
+
+        current.push_str(line_ending);
+    }
+
+    if current == previous {
+        Ok(None)
+    } else {
+        Ok(Some((previous, current)))
+    }
+}
+
+/// Returns the new entry if `entry` points to a moved file, keeping its
+/// relative or absolute style.
+fn rewrite_entry(
+    entry: &str,
+    directory: &Path,
+    moves: &HashMap<PathBuf, PathBuf>,
+) -> Option<String> {
+    let trimmed = entry.trim();
+
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.contains("://")
+    {
+        return None;
+    }
+
+    let entry_path = Path::new(trimmed);
+
+    let target = find_target(moves, &normalize(&directory.join(entry_path)))?;
+
+    let rewritten = if entry_path.is_absolute() {
+        target
+    } else {
+        relative_path(directory, &target)
+    };
+
+    Some(rewritten.to_string_lossy().to_string())
+}
+
+/// Finds the target of `path`, or of the moved directory that contains it.
+fn find_target(
+    moves: &HashMap<PathBuf, PathBuf>,
+    path: &Path,
+) -> Option<PathBuf> {
+    path.ancestors().find_map(|ancestor| {
+        let target = moves.get(ancestor)?;
+
+        // ancestor is an ancestor of path.
+        let relative = path.strip_prefix(ancestor).unwrap();
+
+        // Joining an empty path would add a trailing separator.
+        if relative.as_os_str().is_empty() {
+            Some(target.clone())
+        } else {
+            Some(target.join(relative))
+        }
+    })
+}
+
+/// Resolves `.` and `..` without touching the filesystem, because the files
+/// may already have been moved.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Returns the path of `target` relative to `directory`. Both must be
+/// absolute and normalized.
+pub(crate) fn relative_path(directory: &Path, target: &Path) -> PathBuf {
+    let common = directory
+        .components()
+        .zip(target.components())
+        .take_while(|(left, right)| left == right)
+        .count();
+
+    // Paths on different drives have no relative path.
+    if common == 0 {
+        return target.to_owned();
+    }
+
+    let mut relative = PathBuf::new();
+
+    for _ in directory.components().skip(common) {
+        relative.push("..");
+    }
+
+    for component in target.components().skip(common) {
+        relative.push(component);
+    }
+
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(
+                Path::new("/music/playlists"),
+                Path::new("/music/Artist/Album/Title.mp3")
+            ),
+            PathBuf::from("../Artist/Album/Title.mp3")
+        );
+    }
+
+    #[test]
+    fn test_rewrite_entry() {
+        let moves = HashMap::from([(
+            PathBuf::from("/music/inbox/Title.mp3"),
+            PathBuf::from("/music/Artist/Title.mp3"),
+        )]);
+
+        let directory = Path::new("/music/playlists");
+
+        assert_eq!(
+            rewrite_entry("../inbox/Title.mp3", directory, &moves),
+            Some("../Artist/Title.mp3".to_owned())
+        );
+        assert_eq!(
+            rewrite_entry("/music/inbox/./Title.mp3", directory, &moves),
+            Some("/music/Artist/Title.mp3".to_owned())
+        );
+        assert_eq!(rewrite_entry("#EXTM3U", directory, &moves), None);
+        assert_eq!(rewrite_entry("../Other.mp3", directory, &moves), None);
+    }
+}
@@ -0,0 +1,399 @@
+use super::create_action;
+use crate::cli::args::RenameMode;
+use crate::cli::commands::files::SkippedFile;
+use crate::file::AudioFile;
+use anyhow::anyhow;
+use file_history::Action;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Returns actions that move the files that belong to the files moved by
+/// `actions`.
+///
+/// Files that share a basename with a track, e.g. lyrics or cue sheets,
+/// follow that track. All other files and folders in a directory, e.g.
+/// covers, booklets or scans, follow the album if every track in the
+/// directory moves to the same new directory. Folders that contain audio
+/// files are left alone, and other folders are only moved, never copied or
+/// linked.
+///
+/// Sidecars whose target already exists or is shared with another file stay
+/// where they are and are added to `skipped`.
+pub(crate) fn sidecar_actions(
+    actions: &[Action],
+    mode: RenameMode,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<Action> {
+    let sources: HashSet<&Path> = actions
+        .iter()
+        .map(|action| action.get_src_tgt_unchecked().0)
+        .collect();
+
+    let mut candidates = Vec::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+
+    for action in actions {
+        let (source, target) = action.get_src_tgt_unchecked();
+
+        for path in track_sidecars(source, &sources) {
+            if !claimed.insert(path.clone()) {
+                continue;
+            }
+
+            let sidecar_target = sidecar_target(source, target, &path);
+
+            candidates.push((path, sidecar_target));
+        }
+    }
+
+    for (directory, targets) in group_by_directory(actions) {
+        if targets.len() != 1 || targets.contains(directory) {
+            continue;
+        }
+
+        // The set has exactly one element.
+        let target_directory = targets.into_iter().next().unwrap();
+
+        if let Some(extras) = directory_extras(directory, &sources, &claimed) {
+            for path in extras {
+                if path.is_dir() && mode != RenameMode::Move {
+                    continue;
+                }
+
+                // Entries from read_dir always have a file name.
+                let target = target_directory.join(path.file_name().unwrap());
+
+                candidates.push((path, target));
+            }
+        }
+    }
+
+    // Tracks count as well, so a sidecar never collides with one.
+    let mut target_counts: HashMap<PathBuf, usize> = actions
+        .iter()
+        .map(|action| (action.get_src_tgt_unchecked().1.to_owned(), 1))
+        .collect();
+
+    for (_, target) in &candidates {
+        *target_counts.entry(target.clone()).or_default() += 1;
+    }
+
+    let mut sidecars = Vec::new();
+
+    for (path, target) in candidates {
+        if path == target {
+            continue;
+        }
+
+        let error = if target_counts[&target] > 1 {
+            anyhow!(
+                "Sidecar shares its target '{}' with another file.",
+                target.display()
+            )
+        } else if target.exists() {
+            anyhow!("Sidecar target '{}' already exists.", target.display())
+        } else {
+            match create_action(mode, &path, target) {
+                Ok(action) => {
+                    sidecars.push(action);
+                    continue;
+                }
+                Err(error) => error,
+            }
+        };
+
+        skipped.push(SkippedFile { path, error });
+    }
+
+    sidecars
+}
+
+/// Returns the files next to `source` that are named after its basename
+/// followed by one or more extensions, e.g. "01.lrc" or "01.en.lrc" for
+/// "01.mp3", but not "01. Intro.lrc". Audio files are never sidecars, e.g.
+/// "01.flac" next to "01.mp3".
+fn track_sidecars(source: &Path, sources: &HashSet<&Path>) -> Vec<PathBuf> {
+    let (Some(directory), Some(stem)) = (source.parent(), source.file_stem())
+    else {
+        return Vec::new();
+    };
+
+    let prefix = format!("{}.", stem.to_string_lossy());
+
+    let mut paths: Vec<PathBuf> = read_dir(directory)
+        .into_iter()
+        .filter(|path| {
+            path.is_file()
+                && !sources.contains(path.as_path())
+                && path.file_name().is_some_and(|name| {
+                    name.to_string_lossy()
+                        .strip_prefix(&prefix)
+                        .is_some_and(is_extension_chain)
+                })
+                && !AudioFile::is_audio(path)
+        })
+        .collect();
+
+    paths.sort();
+
+    paths
+}
+
+/// Whether `suffix` consists of extensions only, e.g. "en.lrc".
+fn is_extension_chain(suffix: &str) -> bool {
+    suffix.split('.').all(|extension| {
+        !extension.is_empty()
+            && extension
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Gives a sidecar the basename of `target`, keeping everything after the
+/// basename of `source`, e.g. ".en.lrc".
+fn sidecar_target(source: &Path, target: &Path, sidecar: &Path) -> PathBuf {
+    // source, target and sidecar are all file paths.
+    let source_stem = source.file_stem().unwrap().to_string_lossy();
+    let target_stem = target.file_stem().unwrap().to_string_lossy();
+    let sidecar_name = sidecar.file_name().unwrap().to_string_lossy();
+
+    let suffix = sidecar_name
+        .strip_prefix(source_stem.as_ref())
+        .unwrap_or(&sidecar_name);
+
+    target.with_file_name(format!("{target_stem}{suffix}"))
+}
+
+/// Maps each source directory to the set of target directories.
+fn group_by_directory(actions: &[Action]) -> BTreeMap<&Path, HashSet<&Path>> {
+    let mut directories: BTreeMap<&Path, HashSet<&Path>> = BTreeMap::new();
+
+    for action in actions {
+        let (source, target) = action.get_src_tgt_unchecked();
+
+        if let (Some(source_dir), Some(target_dir)) =
+            (source.parent(), target.parent())
+        {
+            directories
+                .entry(source_dir)
+                .or_default()
+                .insert(target_dir);
+        }
+    }
+
+    directories
+}
+
+/// Returns the entries of `directory` that aren't tracks or track sidecars,
+/// or `None` if it contains audio files that aren't moved.
+fn directory_extras(
+    directory: &Path,
+    sources: &HashSet<&Path>,
+    claimed: &HashSet<PathBuf>,
+) -> Option<Vec<PathBuf>> {
+    let mut extras = Vec::new();
+
+    for path in read_dir(directory) {
+        if sources.contains(path.as_path()) || claimed.contains(&path) {
+            continue;
+        }
+
+        if path.is_dir() {
+            if !contains_audio(&path) {
+                extras.push(path);
+            }
+        } else if AudioFile::is_audio(&path) {
+            return None;
+        } else {
+            extras.push(path);
+        }
+    }
+
+    extras.sort();
+
+    Some(extras)
+}
+
+fn contains_audio(directory: &Path) -> bool {
+    read_dir(directory).into_iter().any(|path| {
+        if path.is_dir() {
+            contains_audio(&path)
+        } else {
+            AudioFile::is_audio(&path)
+        }
+    })
+}
+
+fn read_dir(directory: &Path) -> Vec<PathBuf> {
+    fs::read_dir(directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    #[test]
+    fn test_sidecar_target() {
+        assert_eq!(
+            sidecar_target(
+                Path::new("/in/01 - Title.mp3"),
+                Path::new("/out/Artist - Title.mp3"),
+                Path::new("/in/01 - Title.en.lrc"),
+            ),
+            PathBuf::from("/out/Artist - Title.en.lrc")
+        );
+    }
+
+    #[test]
+    fn test_track_sidecars_skip_audio() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let source = tempdir.child("01 - Title.mp3");
+
+        source.touch()?;
+        tempdir.child("01 - Title.lrc").touch()?;
+        tempdir.child("01 - Title.flac").touch()?;
+        tempdir.child("01 - Title.en.lrc").touch()?;
+        tempdir.child("01 - Title (Live).lrc").touch()?;
+
+        let sources = HashSet::from([source.path()]);
+
+        assert_eq!(
+            track_sidecars(source.path(), &sources),
+            vec![
+                tempdir.child("01 - Title.en.lrc").to_path_buf(),
+                tempdir.child("01 - Title.lrc").to_path_buf(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_track_sidecars_match_whole_stem() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let source = tempdir.child("01.mp3");
+
+        source.touch()?;
+        tempdir.child("01.lrc").touch()?;
+        tempdir.child("01. Intro.lrc").touch()?;
+
+        let sources = HashSet::from([source.path()]);
+
+        assert_eq!(
+            track_sidecars(source.path(), &sources),
+            vec![tempdir.child("01.lrc").to_path_buf()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sidecar_actions() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let source = tempdir.child("in");
+        let target = tempdir.child("out");
+
+        source.child("01 - Title.mp3").touch()?;
+        source.child("01 - Title.lrc").touch()?;
+        source.child("cover.jpg").touch()?;
+        source.child("scans").child("front.png").touch()?;
+
+        let actions = [Action::mv(
+            source.child("01 - Title.mp3"),
+            target.child("Title.mp3"),
+        )];
+
+        let mut skipped = Vec::new();
+
+        let sidecars =
+            sidecar_actions(&actions, RenameMode::Move, &mut skipped);
+
+        let expected = [
+            (source.child("01 - Title.lrc"), target.child("Title.lrc")),
+            (source.child("cover.jpg"), target.child("cover.jpg")),
+            (source.child("scans"), target.child("scans")),
+        ];
+
+        assert!(skipped.is_empty());
+        assert_eq!(sidecars.len(), expected.len());
+
+        for (action, (source, target)) in sidecars.iter().zip(expected) {
+            assert_eq!(
+                action.get_src_tgt_unchecked(),
+                (source.path(), target.path())
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sidecar_conflicts() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let target = tempdir.child("Album");
+
+        for disc in ["CD1", "CD2"] {
+            tempdir.child(disc).child("01.mp3").touch()?;
+            tempdir.child(disc).child("cover.jpg").touch()?;
+        }
+
+        tempdir.child("CD1").child("booklet.pdf").touch()?;
+        tempdir.child("CD2").child("notes.txt").touch()?;
+        target.child("notes.txt").touch()?;
+
+        let actions = [
+            Action::mv(
+                tempdir.child("CD1").child("01.mp3"),
+                target.child("1.01.mp3"),
+            ),
+            Action::mv(
+                tempdir.child("CD2").child("01.mp3"),
+                target.child("2.01.mp3"),
+            ),
+        ];
+
+        let mut skipped = Vec::new();
+
+        let sidecars =
+            sidecar_actions(&actions, RenameMode::Move, &mut skipped);
+
+        // Both covers would end up as Album/cover.jpg, and Album/notes.txt
+        // already exists.
+        assert_eq!(sidecars.len(), 1);
+        assert_eq!(
+            sidecars[0].get_src_tgt_unchecked(),
+            (
+                tempdir.child("CD1").child("booklet.pdf").path(),
+                target.child("booklet.pdf").path()
+            )
+        );
+
+        let mut skipped: Vec<PathBuf> =
+            skipped.into_iter().map(|file| file.path).collect();
+        skipped.sort();
+
+        assert_eq!(
+            skipped,
+            vec![
+                tempdir.child("CD1").child("cover.jpg").to_path_buf(),
+                tempdir.child("CD2").child("cover.jpg").to_path_buf(),
+                tempdir.child("CD2").child("notes.txt").to_path_buf(),
+            ]
+        );
+
+        Ok(())
+    }
+}
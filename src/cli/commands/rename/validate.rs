@@ -2,6 +2,7 @@ use crate::cli::args::Args;
 use anyhow::{bail, Result};
 use file_history::Action;
 use std::collections::HashMap;
+use std::fmt::Write;
 use std::path::Path;
 
 pub(crate) fn validate_actions(actions: &[Action]) -> Result<()> {
@@ -49,8 +50,10 @@ fn format_collisions(collisions: &HashMap<&Path, Vec<&Path>>) -> String {
             break;
         }
         let length = collisions.len();
-        string += &format!(
-            "{} is pointed to by {} file{}{}:\n",
+        // Writing to a String can't fail.
+        let _ = writeln!(
+            string,
+            "{} is pointed to by {} file{}{}:",
             path.display(),
             length,
             if length > 1 { "s" } else { "" },
@@ -65,7 +68,7 @@ fn format_collisions(collisions: &HashMap<&Path, Vec<&Path>>) -> String {
             if i >= Args::DEFAULT_PREVIEW_AMOUNT {
                 break;
             }
-            string += &format!("{}\n", path.display());
+            let _ = writeln!(string, "{}", path.display());
         }
         string += "\n";
     }
@@ -0,0 +1,235 @@
+use super::files::{gather_files, print_skipped_files, SkippedFile};
+use super::tag_changes::{print_changes_preview, write_tags, TagChanges};
+use crate::cli::{ui, Config};
+use crate::file::{
+    item_key_from_keyword, parse_raw_key, tag_type_name, AudioFile,
+};
+use crate::script::Script;
+use crate::tapr::run_script;
+use anyhow::{anyhow, bail, Result};
+use file_history::{History, TagValue};
+use indicatif::ProgressIterator;
+use lofty::{ItemKey, TagType};
+use std::sync::Arc;
+use tapr::Value;
+
+pub(crate) fn retag(
+    preview: bool,
+    config: &Config,
+    recursion_depth: usize,
+    fail_fast: bool,
+    name: &str,
+    arguments: &[String],
+) -> Result<()> {
+    let mut history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    let mut script = config.get_script(name)?;
+
+    let mut skipped = Vec::new();
+
+    let files = gather_files(
+        recursion_depth,
+        config.tag_options(),
+        fail_fast,
+        &mut skipped,
+    )?;
+
+    script.add_arguments_to_node(Script::RETAG, arguments)?;
+
+    let changes = interpret_files(&script, files, fail_fast, &mut skipped)?;
+
+    let result = if changes.is_empty() {
+        println!("There are no tags to update.");
+        Ok(())
+    } else {
+        print_changes_preview(
+            &changes,
+            crate::cli::Args::DEFAULT_PREVIEW_AMOUNT,
+        );
+
+        write_tags(preview, &mut history, changes)
+    };
+
+    print_skipped_files(&skipped);
+
+    result
+}
+
+fn interpret_files(
+    script: &Script,
+    files: Vec<AudioFile>,
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<Vec<TagChanges>> {
+    let bar = ui::create_progressbar(
+        files.len() as u64,
+        "Interpreting files...",
+        "Interpreted files.",
+        false,
+    );
+
+    let mut changes = Vec::new();
+
+    for audiofile in files.into_iter().progress_with(bar) {
+        let path = audiofile.path().to_owned();
+
+        match changes_from_file(script, audiofile) {
+            Ok(Some(tag_changes)) => changes.push(tag_changes),
+            Ok(None) => (),
+            Err(error) if !fail_fast => {
+                skipped.push(SkippedFile { path, error });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Runs the script for `audiofile` and compares the returned values to the
+/// ones in its primary tag block. Returns `None` if nothing changed.
+fn changes_from_file(
+    script: &Script,
+    audiofile: AudioFile,
+) -> Result<Option<TagChanges>> {
+    let audiofile = Arc::new(audiofile);
+    let tag_type = audiofile.primary_tag_type();
+
+    let value = run_script(script, audiofile.clone())?;
+
+    let map = match value {
+        Value::Nil => return Ok(None),
+        Value::Map(map) => map,
+        _ => bail!("Script did not return a map of tags."),
+    };
+
+    let mut entries = map
+        .into_iter()
+        .map(|(key, value)| {
+            Ok((item_key_from_map_key(tag_type, &key)?, values(value)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    entries.sort_by(|(left, _), (right, _)| {
+        left.map_key(tag_type, true)
+            .cmp(&right.map_key(tag_type, true))
+    });
+
+    let mut previous = Vec::new();
+    let mut current = Vec::new();
+
+    for (key, values) in entries {
+        let Some(native_key) = key.map_key(tag_type, true) else {
+            bail!(
+                "{:?} can't be written to {} tags.",
+                key,
+                tag_type_name(tag_type)
+            )
+        };
+
+        let previous_values = audiofile.tag_values(tag_type, &key);
+
+        if previous_values != values {
+            previous.push(TagValue::new(tag_type, native_key, previous_values));
+            current.push(TagValue::new(tag_type, native_key, values));
+        }
+    }
+
+    if current.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(TagChanges {
+        path: audiofile.path().to_owned(),
+        previous,
+        current,
+    }))
+}
+
+/// Keywords are mapped like those of `tags`, strings are used as native keys
+/// of the primary tag block.
+fn item_key_from_map_key(tag_type: TagType, key: &Value) -> Result<ItemKey> {
+    match key {
+        Value::Keyword(keyword) => item_key_from_keyword(keyword)
+            .ok_or_else(|| anyhow!("Unknown tag keyword :{keyword}")),
+        Value::String(key) => {
+            Ok(ItemKey::from_key(tag_type, parse_raw_key(tag_type, key)))
+        }
+        _ => bail!("Tag keys must be keywords or strings."),
+    }
+}
+
+/// Strings and numbers are written as a single value, lists as multiple
+/// values, and nil removes the tag.
+fn values(value: Value) -> Result<Vec<String>> {
+    match value {
+        Value::Nil => Ok(Vec::new()),
+        Value::List(values) => {
+            values.into_iter().map(value_to_string).collect()
+        }
+        value => Ok(vec![value_to_string(value)?]),
+    }
+}
+
+fn value_to_string(value: Value) -> Result<String> {
+    match value {
+        Value::String(string) => Ok(string),
+        Value::Number(number) => Ok(number.to_string()),
+        _ => Err(anyhow!(
+            "Tag values must be strings, numbers, lists of those or nil."
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_key_from_map_key() -> Result<()> {
+        assert_eq!(
+            item_key_from_map_key(
+                TagType::Id3v2,
+                &Value::Keyword("artist".to_owned())
+            )?,
+            ItemKey::TrackArtist
+        );
+        assert_eq!(
+            item_key_from_map_key(
+                TagType::VorbisComments,
+                &Value::Keyword("album_artist".to_owned())
+            )?,
+            ItemKey::AlbumArtist
+        );
+        assert_eq!(
+            item_key_from_map_key(TagType::Id3v2, &"TIT2".into())?,
+            ItemKey::TrackTitle
+        );
+        assert_eq!(
+            item_key_from_map_key(
+                TagType::VorbisComments,
+                &"RELEASETYPE".into()
+            )?,
+            ItemKey::Unknown("RELEASETYPE".to_owned())
+        );
+        assert_eq!(
+            item_key_from_map_key(TagType::Id3v2, &"TXXX:RELEASETYPE".into())?,
+            ItemKey::Unknown("RELEASETYPE".to_owned())
+        );
+
+        // Quotes and colons are part of native keys.
+        assert_eq!(
+            item_key_from_map_key(TagType::VorbisComments, &":artist".into())?,
+            ItemKey::Unknown(":artist".to_owned())
+        );
+
+        assert!(item_key_from_map_key(
+            TagType::Id3v2,
+            &Value::Keyword("artits".to_owned())
+        )
+        .is_err());
+        assert!(item_key_from_map_key(TagType::Id3v2, &Value::Nil).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::cli::ui;
+use crate::file::tag_type_name;
+use anyhow::Result;
+use file_history::{Action, History, TagValue};
+use indicatif::ProgressIterator;
+use std::path::PathBuf;
+
+/// The tags of a single file that will be changed.
+pub(crate) struct TagChanges {
+    pub(crate) path: PathBuf,
+    pub(crate) previous: Vec<TagValue>,
+    pub(crate) current: Vec<TagValue>,
+}
+
+pub(crate) fn print_changes_preview(
+    changes: &[TagChanges],
+    preview_amount: usize,
+) {
+    let length = changes.len();
+
+    println!(
+        "\nPreviewing {} files:",
+        if length <= preview_amount {
+            length.to_string()
+        } else {
+            format!("{preview_amount}/{length}")
+        }
+    );
+
+    let step = std::cmp::max(length / preview_amount, 1);
+
+    for tag_changes in changes.iter().step_by(step).take(preview_amount) {
+        println!("{}", tag_changes.path.display());
+
+        for (previous, current) in
+            tag_changes.previous.iter().zip(&tag_changes.current)
+        {
+            println!(
+                "  {}: {:?} -> {:?} [{}]",
+                current.key(),
+                previous.values(),
+                current.values(),
+                current.tag_type().map_or("unknown", tag_type_name)
+            );
+        }
+    }
+
+    println!();
+}
+
+pub(crate) fn write_tags(
+    preview: bool,
+    history: &mut History,
+    changes: Vec<TagChanges>,
+) -> Result<()> {
+    let bar = ui::create_progressbar(
+        changes.len() as u64,
+        "Writing tags...",
+        "Wrote tags.",
+        preview,
+    );
+
+    for tag_changes in changes.into_iter().progress_with(bar) {
+        if preview {
+            continue;
+        }
+
+        let action = Action::update_tags(
+            tag_changes.path,
+            tag_changes.previous,
+            tag_changes.current,
+        );
+
+        history.apply(action)?;
+    }
+
+    if !preview {
+        history.save()?;
+    }
+
+    Ok(())
+}
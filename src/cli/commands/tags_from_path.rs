@@ -0,0 +1,132 @@
+use super::files::{gather_files, print_skipped_files, SkippedFile};
+use super::tag_changes::{print_changes_preview, write_tags, TagChanges};
+use crate::cli::{ui, Config};
+use crate::file::{tag_type_name, AudioFile, TagOptions};
+use crate::path_pattern::PathPattern;
+use anyhow::{anyhow, bail, Result};
+use file_history::{History, TagValue};
+use indicatif::ProgressIterator;
+
+#[allow(clippy::fn_params_excessive_bools)]
+pub(crate) fn tags_from_path(
+    preview: bool,
+    config: &Config,
+    recursion_depth: usize,
+    fail_fast: bool,
+    overwrite: bool,
+    pattern: &str,
+    is_regex: bool,
+) -> Result<()> {
+    let pattern = PathPattern::parse(pattern, is_regex)?;
+
+    let mut history = History::load(config.path(), Config::HISTORY_NAME)?;
+
+    // Files without tags can only be read if they match the pattern.
+    let tag_options = TagOptions::new(
+        config.tag_options().priority().to_vec(),
+        config.tag_options().separators().to_vec(),
+        Some(pattern.clone()),
+    );
+
+    let mut skipped = Vec::new();
+
+    let files =
+        gather_files(recursion_depth, &tag_options, fail_fast, &mut skipped)?;
+
+    let changes =
+        propose_changes(&pattern, files, overwrite, fail_fast, &mut skipped)?;
+
+    let result = if changes.is_empty() {
+        println!("There are no tags to update.");
+        Ok(())
+    } else {
+        print_changes_preview(
+            &changes,
+            crate::cli::Args::DEFAULT_PREVIEW_AMOUNT,
+        );
+
+        write_tags(preview, &mut history, changes)
+    };
+
+    print_skipped_files(&skipped);
+
+    result
+}
+
+fn propose_changes(
+    pattern: &PathPattern,
+    files: Vec<AudioFile>,
+    overwrite: bool,
+    fail_fast: bool,
+    skipped: &mut Vec<SkippedFile>,
+) -> Result<Vec<TagChanges>> {
+    let bar = ui::create_progressbar(
+        files.len() as u64,
+        "Matching paths...",
+        "Matched paths.",
+        false,
+    );
+
+    let mut changes = Vec::new();
+
+    for audiofile in files.into_iter().progress_with(bar) {
+        let path = audiofile.path().to_owned();
+
+        match changes_from_path(pattern, &audiofile, overwrite) {
+            Ok(Some(tag_changes)) => changes.push(tag_changes),
+            Ok(None) => (),
+            Err(error) if !fail_fast => {
+                skipped.push(SkippedFile { path, error });
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Compares the values in the path of `audiofile` to the ones in its primary
+/// tag block. Unless `overwrite` is set, only empty tags are filled in.
+fn changes_from_path(
+    pattern: &PathPattern,
+    audiofile: &AudioFile,
+    overwrite: bool,
+) -> Result<Option<TagChanges>> {
+    let values = pattern
+        .captures(audiofile.path())
+        .ok_or_else(|| anyhow!("Path does not match the pattern."))?;
+
+    let tag_type = audiofile.primary_tag_type();
+
+    let mut previous = Vec::new();
+    let mut current = Vec::new();
+
+    for (key, value) in values {
+        let Some(native_key) = key.map_key(tag_type, true) else {
+            bail!(
+                "{:?} can't be written to {} tags.",
+                key,
+                tag_type_name(tag_type)
+            )
+        };
+
+        let previous_values = audiofile.tag_values(tag_type, &key);
+
+        if (overwrite || previous_values.is_empty())
+            && previous_values != [value.as_str()]
+        {
+            previous.push(TagValue::new(tag_type, native_key, previous_values));
+            current.push(TagValue::new(tag_type, native_key, vec![value]));
+        }
+    }
+
+    if current.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(TagChanges {
+        path: audiofile.path().to_owned(),
+        previous,
+        current,
+    }))
+}
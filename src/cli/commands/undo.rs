@@ -23,7 +23,7 @@ pub(crate) fn undo(
 
     if preview {
         let pp = Config::PREVIEW_PREFIX;
-        println!("{pp}{mode_string} {times} renames.");
+        println!("{pp}{mode_string} {times} changes.");
     } else {
         let action_counts = match mode {
             UndoMode::Undo => history.undo(times)?,
@@ -33,15 +33,9 @@ pub(crate) fn undo(
         // TODO? some sort of rollback logic for errors during undo/redo?
         history.save()?;
 
-        println!("{} {} renames:", mode_string, action_counts.len());
+        println!("{} {} changes:", mode_string, action_counts.len());
         for (i, action_count) in action_counts.into_iter().enumerate() {
-            println!(
-                "{}: {} moves, {} dirs created, {} dirs removed",
-                i + 1,
-                action_count.mv,
-                action_count.mkdir,
-                action_count.rmdir
-            );
+            println!("{}: {action_count}", i + 1);
         }
     }
 
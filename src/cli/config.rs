@@ -1,4 +1,5 @@
 use crate::cli::ui;
+use crate::file::TagOptions;
 use crate::script::Script;
 use anyhow::{bail, Result};
 use std::fs;
@@ -6,6 +7,7 @@ use std::path::{Path, PathBuf};
 
 pub(crate) struct Config {
     path: PathBuf,
+    tag_options: TagOptions,
 }
 
 impl Config {
@@ -13,9 +15,10 @@ impl Config {
     pub(crate) const PREVIEW_PREFIX: &'static str = "[P] ";
     pub(crate) const SCRIPT_EXTENSION: &'static str = "tapr";
 
-    pub(crate) fn new(path: &Path) -> Result<Self> {
+    pub(crate) fn new(path: &Path, tag_options: TagOptions) -> Result<Self> {
         let config = Self {
             path: path.to_owned(),
+            tag_options,
         };
 
         Config::create_dir(&config.path)?;
@@ -80,6 +83,10 @@ impl Config {
         &self.path
     }
 
+    pub(crate) fn tag_options(&self) -> &TagOptions {
+        &self.tag_options
+    }
+
     pub(crate) fn get_scripts(&self) -> Result<Vec<Script>> {
         let paths = self.get_script_paths()?;
 
@@ -124,10 +131,8 @@ impl Config {
     }
 
     fn get_script_paths(&self) -> Result<Vec<PathBuf>> {
-        let predicate: fn(&Path) -> bool = |p| {
-            p.extension()
-                .map_or(false, |s| s == Config::SCRIPT_EXTENSION)
-        };
+        let predicate: fn(&Path) -> bool =
+            |p| p.extension().is_some_and(|s| s == Config::SCRIPT_EXTENSION);
 
         let mut paths = Config::search_path(self.path(), 0, &predicate, None);
         paths.extend(Config::search_path(
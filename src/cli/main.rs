@@ -1,6 +1,8 @@
-use crate::cli::args::Command;
-use crate::cli::commands::{self, UndoMode};
+use crate::cli::args::{Command, HistoryCommand};
+use crate::cli::commands::{self, RenameOptions, UndoMode};
 use crate::cli::{ui, Args, Config};
+use crate::file::TagOptions;
+use crate::path_pattern::PathPattern;
 use anyhow::Result;
 
 /// Main entrypoint for taprtools
@@ -14,18 +16,76 @@ pub fn main(preview_override: bool) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_lines)]
 fn select_command(args: Args) -> Result<()> {
-    let config = if let Some(path) = &args.config {
-        Config::new(path)?
+    let config_path = if let Some(path) = args.config {
+        path
     } else {
-        Config::new(&Config::default_path()?)?
+        Config::default_path()?
     };
 
+    let path_pattern = match (&args.path_pattern, &args.path_regex) {
+        (Some(pattern), _) => Some(PathPattern::new(pattern)?),
+        (None, Some(regex)) => Some(PathPattern::from_regex(regex)?),
+        (None, None) => None,
+    };
+
+    let tag_options =
+        TagOptions::new(args.tag_priority, args.split_on, path_pattern);
+
+    let config = Config::new(&config_path, tag_options)?;
+
     match args.command {
+        Command::CleanTags {
+            preview,
+            recurse,
+            fail_fast,
+        } => commands::clean_tags(preview, &config, recurse, fail_fast),
         Command::ClearHistory { preview } => {
             commands::clear_history(preview, &config)
         }
+        Command::ConvertTags {
+            preview,
+            recurse,
+            fail_fast,
+            target,
+            keep,
+        } => commands::convert_tags(
+            preview, &config, recurse, fail_fast, target, &keep,
+        ),
+        Command::ExtractCovers {
+            preview,
+            recurse,
+            fail_fast,
+            name,
+        } => commands::extract_covers(
+            preview, &config, recurse, fail_fast, &name,
+        ),
+        Command::History {
+            command: HistoryCommand::Gc { preview },
+        } => commands::collect_garbage(preview, &config),
         Command::ListScripts => commands::list_scripts(&config),
+        Command::ListFormats => {
+            commands::list_formats();
+            Ok(())
+        }
+        Command::Inspect { paths } => commands::inspect(&config, &paths),
+        Command::Playlist {
+            preview,
+            recurse,
+            fail_fast,
+            output,
+            name,
+            arguments,
+        } => commands::playlist(
+            preview,
+            &config,
+            recurse,
+            fail_fast,
+            output.as_deref(),
+            &name,
+            &arguments,
+        ),
         Command::Undo { preview, times } => {
             commands::undo(preview, &config, UndoMode::Undo, times)
         }
@@ -35,12 +95,55 @@ fn select_command(args: Args) -> Result<()> {
         Command::Rename {
             preview,
             recurse,
+            keep_extension,
+            input,
+            output,
+            fail_fast,
+            no_sidecars,
+            playlists,
+            mode,
+            remove_leftovers,
+            trash,
             name,
             arguments,
-        } => commands::rename(preview, &config, recurse, &name, &arguments),
+        } => {
+            let options = RenameOptions {
+                inputs: input,
+                output,
+                recursion_depth: recurse,
+                keep_extension,
+                fail_fast,
+                sidecars: !no_sidecars,
+                playlists,
+                mode,
+                remove_leftovers,
+                trash,
+            };
+
+            commands::rename(preview, &config, &options, &name, &arguments)
+        }
 
+        Command::Retag {
+            preview,
+            recurse,
+            fail_fast,
+            name,
+            arguments,
+        } => commands::retag(
+            preview, &config, recurse, fail_fast, &name, &arguments,
+        ),
         Command::Seed { preview, force } => {
             commands::seed(preview, force, &config)
         }
+        Command::TagsFromPath {
+            preview,
+            recurse,
+            fail_fast,
+            overwrite,
+            regex,
+            pattern,
+        } => commands::tags_from_path(
+            preview, &config, recurse, fail_fast, overwrite, &pattern, regex,
+        ),
     }
 }
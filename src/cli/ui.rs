@@ -1,14 +1,34 @@
 use crate::cli::Config;
+use anyhow::Result;
 use file_history::Action;
 use indicatif::{
     ProgressBar as IProgressBar, ProgressDrawTarget, ProgressFinish,
     ProgressStyle,
 };
+use std::io::{IsTerminal, Write};
 
 pub(crate) fn print_error(error: &anyhow::Error) {
     println!("An error occurred:\n{error}");
 }
 
+/// Asks the user a yes/no question. Returns `false` if stdin is not a
+/// terminal.
+pub(crate) fn confirm(question: &str) -> Result<bool> {
+    let stdin = std::io::stdin();
+
+    if !stdin.is_terminal() {
+        return Ok(false);
+    }
+
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    stdin.read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
 pub(crate) struct AudioFileSpinner {
     spinner: IProgressBar,
 }
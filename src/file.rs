@@ -1,13 +1,307 @@
 #![allow(clippy::upper_case_acronyms)]
+use crate::path_pattern::PathPattern;
 use crate::tags::Tags;
-use anyhow::{anyhow, Result};
-use lofty::{ItemKey, Tag, TaggedFileExt};
+use anyhow::{anyhow, bail, Result};
+use file_history::TAG_TYPES;
+pub(crate) use file_history::{tag_type_from_name, tag_type_name};
+use lofty::{
+    AudioFile as _, FileProperties, FileType, ItemKey, Picture, Probe, Tag,
+    TagItem, TagType, TaggedFileExt,
+};
+use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
+/// An audio container format that `AudioFile` can read.
+#[derive(Debug)]
+pub(crate) struct Format {
+    name: &'static str,
+    file_type: FileType,
+    extensions: &'static [&'static str],
+    codec: &'static str,
+    lossless: bool,
+}
+
+impl Format {
+    pub(crate) fn name(&self) -> &str {
+        self.name
+    }
+
+    pub(crate) fn file_type(&self) -> &FileType {
+        &self.file_type
+    }
+
+    pub(crate) fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+
+    /// The preferred extension for this format.
+    pub(crate) fn canonical_extension(&self) -> &str {
+        self.extensions[0]
+    }
+
+    pub(crate) fn from_file_type(
+        file_type: &FileType,
+    ) -> Option<&'static Format> {
+        FORMATS
+            .iter()
+            .find(|format| format.file_type() == file_type)
+    }
+}
+
+/// Registry of all supported formats.
+pub(crate) static FORMATS: [Format; 12] = [
+    Format {
+        name: "AAC (ADTS)",
+        file_type: FileType::Aac,
+        extensions: &["aac"],
+        codec: "AAC",
+        lossless: false,
+    },
+    Format {
+        name: "AIFF",
+        file_type: FileType::Aiff,
+        extensions: &["aiff", "aif", "aifc", "afc"],
+        codec: "PCM",
+        lossless: true,
+    },
+    Format {
+        name: "Monkey's Audio",
+        file_type: FileType::Ape,
+        extensions: &["ape"],
+        codec: "Monkey's Audio",
+        lossless: true,
+    },
+    Format {
+        name: "FLAC",
+        file_type: FileType::Flac,
+        extensions: &["flac"],
+        codec: "FLAC",
+        lossless: true,
+    },
+    Format {
+        name: "MP4",
+        file_type: FileType::Mp4,
+        extensions: &["m4a", "m4b", "m4p", "m4r", "m4v", "mp4", "3gp"],
+        codec: "AAC",
+        lossless: false,
+    },
+    Format {
+        name: "Musepack",
+        file_type: FileType::Mpc,
+        extensions: &["mpc", "mp+", "mpp"],
+        codec: "Musepack",
+        lossless: false,
+    },
+    Format {
+        name: "MPEG",
+        file_type: FileType::Mpeg,
+        extensions: &["mp3", "mp2", "mp1"],
+        codec: "MP3",
+        lossless: false,
+    },
+    Format {
+        name: "Opus",
+        file_type: FileType::Opus,
+        extensions: &["opus"],
+        codec: "Opus",
+        lossless: false,
+    },
+    Format {
+        name: "Speex",
+        file_type: FileType::Speex,
+        extensions: &["spx"],
+        codec: "Speex",
+        lossless: false,
+    },
+    Format {
+        name: "Ogg Vorbis",
+        file_type: FileType::Vorbis,
+        extensions: &["ogg", "oga"],
+        codec: "Vorbis",
+        lossless: false,
+    },
+    Format {
+        name: "WAV",
+        file_type: FileType::Wav,
+        extensions: &["wav", "wave"],
+        codec: "PCM",
+        lossless: true,
+    },
+    Format {
+        name: "WavPack",
+        file_type: FileType::WavPack,
+        extensions: &["wv"],
+        codec: "WavPack",
+        lossless: true,
+    },
+];
+
+/// Extensions of files that are commonly found next to audio files, but are
+/// never audio themselves. These are skipped without reading them.
+const NON_AUDIO_EXTENSIONS: [&str; 30] = [
+    "accurip", "avi", "bmp", "cue", "db", "gif", "htm", "html", "ini", "jpeg",
+    "jpg", "json", "log", "lrc", "m3u", "m3u8", "md", "md5", "mkv", "mov",
+    "nfo", "pdf", "pls", "png", "sfv", "tif", "tiff", "txt", "webp", "xml",
+];
+
+/// Maps a script keyword to the key it reads from and writes to.
+pub(crate) fn item_key_from_keyword(keyword: &str) -> Option<ItemKey> {
+    let key = match keyword {
+        "album" => ItemKey::AlbumTitle,
+        "album_artist" | "albumartist" => ItemKey::AlbumArtist,
+        "album_sort" | "albumsort" => ItemKey::AlbumTitleSortOrder,
+        "artist" => ItemKey::TrackArtist,
+        "genre" => ItemKey::Genre,
+        "title" => ItemKey::TrackTitle,
+        "year" | "date" => ItemKey::RecordingDate,
+        "track_number" | "tracknumber" => ItemKey::TrackNumber,
+        "disc_number" | "discnumber" | "disk_number" | "disknumber" => {
+            ItemKey::DiscNumber
+        }
+        "total_tracks" | "totaltracks" | "track_total" | "tracktotal" => {
+            ItemKey::TrackTotal
+        }
+        "total_discs" | "totaldiscs" | "disc_total" | "disctotal" => {
+            ItemKey::DiscTotal
+        }
+        "artist_sort" | "artistsort" => ItemKey::TrackArtistSortOrder,
+        "album_artist_sort" | "albumartistsort" => {
+            ItemKey::AlbumArtistSortOrder
+        }
+        "title_sort" | "titlesort" => ItemKey::TrackTitleSortOrder,
+        "composer" => ItemKey::Composer,
+        "conductor" => ItemKey::Conductor,
+        "performer" => ItemKey::Performer,
+        "label" => ItemKey::Label,
+        "catalog_number" | "catalognumber" => ItemKey::CatalogNumber,
+        "isrc" => ItemKey::Isrc,
+        "barcode" => ItemKey::Barcode,
+        "bpm" => ItemKey::Bpm,
+        "key" | "initial_key" | "initialkey" => ItemKey::InitialKey,
+        "comment" => ItemKey::Comment,
+        "compilation" => ItemKey::FlagCompilation,
+        "work" => ItemKey::Work,
+        "movement" => ItemKey::Movement,
+        "movement_number" | "movementnumber" => ItemKey::MovementNumber,
+        "musicbrainz_album_id" | "musicbrainz_albumid" => {
+            ItemKey::MusicBrainzReleaseId
+        }
+        "musicbrainz_release_group_id" | "musicbrainz_releasegroupid" => {
+            ItemKey::MusicBrainzReleaseGroupId
+        }
+        "musicbrainz_artist_id" | "musicbrainz_artistid" => {
+            ItemKey::MusicBrainzArtistId
+        }
+        "musicbrainz_track_id" | "musicbrainz_trackid" => {
+            ItemKey::MusicBrainzRecordingId
+        }
+        "musicbrainz_release_track_id" | "musicbrainz_releasetrackid" => {
+            ItemKey::MusicBrainzTrackId
+        }
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Native keys of the release type, which lofty doesn't map. Vorbis comments
+/// and APE share the plain keys.
+const RELEASE_TYPE_KEYS: [&str; 5] = [
+    "TXXX:MusicBrainz Album Type",
+    "TXXX:RELEASETYPE",
+    "RELEASETYPE",
+    "MUSICBRAINZ_ALBUMTYPE",
+    "----:com.apple.iTunes:MusicBrainz Album Type",
+];
+
+/// Native keys of the release country, which lofty doesn't map. Vorbis
+/// comments and APE share the plain key.
+const COUNTRY_KEYS: [&str; 4] = [
+    "TXXX:MusicBrainz Album Release Country",
+    "TXXX:RELEASECOUNTRY",
+    "RELEASECOUNTRY",
+    "----:com.apple.iTunes:MusicBrainz Album Release Country",
+];
+
+/// Returns the native keys for keywords that have no `ItemKey`.
+pub(crate) fn raw_keys_from_keyword(
+    keyword: &str,
+) -> Option<&'static [&'static str]> {
+    match keyword {
+        "release_type" | "releasetype" => Some(&RELEASE_TYPE_KEYS),
+        "country" | "release_country" | "releasecountry" => Some(&COUNTRY_KEYS),
+        _ => None,
+    }
+}
+
+/// Reads the major version from the `ID3v2` header at the start of the file,
+/// if there is one.
+pub(crate) fn id3v2_major_version(path: &Path) -> Result<Option<u8>> {
+    let mut header = [0; 4];
+
+    let mut file = File::open(path)?;
+
+    if file.read_exact(&mut header).is_err() || &header[..3] != b"ID3" {
+        return Ok(None);
+    }
+
+    Ok(Some(header[3]))
+}
+
+/// Options that control how tags are read.
+#[derive(Debug, Clone)]
+pub(crate) struct TagOptions {
+    priority: Vec<TagType>,
+    separators: Vec<String>,
+    path_pattern: Option<PathPattern>,
+}
+
+impl TagOptions {
+    /// Creates new `TagOptions`. An empty `priority` uses the default
+    /// priority. Values read from the path with `path_pattern` override all
+    /// tag blocks.
+    pub(crate) fn new(
+        priority: Vec<TagType>,
+        separators: Vec<String>,
+        path_pattern: Option<PathPattern>,
+    ) -> Self {
+        let priority = if priority.is_empty() {
+            TAG_TYPES.iter().map(|(_, tag_type)| *tag_type).collect()
+        } else {
+            priority
+        };
+
+        TagOptions {
+            priority,
+            separators,
+            path_pattern,
+        }
+    }
+
+    pub(crate) fn priority(&self) -> &[TagType] {
+        &self.priority
+    }
+
+    pub(crate) fn separators(&self) -> &[String] {
+        &self.separators
+    }
+
+    pub(crate) fn path_pattern(&self) -> Option<&PathPattern> {
+        self.path_pattern.as_ref()
+    }
+}
+
 pub(crate) struct AudioFile {
     path: PathBuf,
-    tag: Tag,
-    extension: String,
+    // Ordered by priority.
+    tags: Vec<Tag>,
+    // Values read from the path, which take precedence over `tags`.
+    overrides: Option<Tag>,
+    properties: FileProperties,
+    format: &'static Format,
+    extension: Option<String>,
+    separators: Vec<String>,
 }
 
 impl std::fmt::Debug for AudioFile {
@@ -19,73 +313,845 @@ impl std::fmt::Debug for AudioFile {
 }
 
 impl AudioFile {
-    pub(crate) const SUPPORTED_EXTENSIONS: [&'static str; 2] = ["mp3", "ogg"];
-
-    pub(crate) fn new(path: &Path) -> Result<AudioFile> {
+    /// Reads all tag blocks in `path`, ordered by the priority in `options`.
+    /// Blocks not mentioned there are consulted last.
+    pub(crate) fn new(path: &Path, options: &TagOptions) -> Result<AudioFile> {
         let path = path.to_owned();
-        let tagged_file = lofty::read_from_path(&path)?;
-        let tag = tagged_file
-            .primary_tag()
-            .ok_or_else(|| {
-                anyhow!("Unable to read primary tag for '{}'", path.display())
-            })?
-            .clone();
+        let tagged_file = Probe::open(&path)?.guess_file_type()?.read()?;
+
+        let file_type = tagged_file.file_type();
+
+        let format = Format::from_file_type(&file_type).ok_or_else(|| {
+            anyhow!(
+                "Format {:?} of '{}' is not supported",
+                file_type,
+                path.display()
+            )
+        })?;
+
+        let mut tags = tagged_file.tags().to_vec();
+
+        let overrides = options
+            .path_pattern()
+            .and_then(|pattern| pattern.captures(&path))
+            .map(|values| {
+                let tag_type = file_type.primary_tag_type();
+                let mut tag = Tag::new(tag_type);
+
+                for (key, value) in values {
+                    if !tag.insert_text(key.clone(), value) {
+                        bail!(
+                            "{:?} can't be read from the path of '{}', \
+                             {} tags don't support it.",
+                            key,
+                            path.display(),
+                            tag_type_name(tag_type)
+                        );
+                    }
+                }
+
+                Ok(tag)
+            })
+            .transpose()?;
 
-        let extension = path.extension().unwrap().to_string_lossy().to_string();
+        if tags.is_empty() && overrides.is_none() {
+            bail!("Unable to read any tags for '{}'", path.display());
+        }
+
+        let priority = options.priority();
+
+        tags.sort_by_key(|tag| {
+            priority
+                .iter()
+                .position(|tag_type| *tag_type == tag.tag_type())
+                .unwrap_or(priority.len())
+        });
+
+        let properties = tagged_file.properties().clone();
+
+        let extension =
+            path.extension().map(|e| e.to_string_lossy().to_string());
 
         Ok(AudioFile {
             path,
-            tag,
+            tags,
+            overrides,
+            properties,
+            format,
             extension,
+            separators: options.separators().to_vec(),
         })
     }
 
+    /// Determines the format of `path` by its contents, falling back on its
+    /// extension.
+    pub(crate) fn probe(path: &Path) -> Option<&'static Format> {
+        let probe = Probe::open(path).ok()?.guess_file_type().ok()?;
+
+        Format::from_file_type(probe.file_type().as_ref()?)
+    }
+
+    /// Decides whether `path` is an audio file. Files with a known non-audio
+    /// extension are rejected without reading them. All other files are
+    /// probed every time, so the answer is never stale.
+    pub(crate) fn is_audio(path: &Path) -> bool {
+        let is_non_audio = path.extension().is_some_and(|extension| {
+            NON_AUDIO_EXTENSIONS
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(&extension.to_string_lossy()))
+        });
+
+        !is_non_audio && AudioFile::probe(path).is_some()
+    }
+
     pub(crate) fn path(&self) -> &Path {
         &self.path
     }
 
-    pub(crate) fn extension(&self) -> &str {
-        self.extension.as_ref()
+    pub(crate) fn format(&self) -> &'static Format {
+        self.format
+    }
+
+    pub(crate) fn properties(&self) -> &FileProperties {
+        &self.properties
+    }
+
+    /// The codec of the audio stream.
+    pub(crate) fn codec(&self) -> &str {
+        // MP4 can contain either AAC or ALAC, but only ALAC reports a bit
+        // depth.
+        if self.is_alac() {
+            "ALAC"
+        } else {
+            self.format.codec
+        }
+    }
+
+    pub(crate) fn is_lossless(&self) -> bool {
+        self.format.lossless || self.is_alac()
+    }
+
+    fn is_alac(&self) -> bool {
+        self.format.file_type == FileType::Mp4
+            && self.properties.bit_depth().is_some()
+    }
+
+    /// The original extension of this file, if any.
+    pub(crate) fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// Returns every item in the merged view, along with the tag block it
+    /// was read from.
+    pub(crate) fn merged_items(&self) -> Vec<(&TagItem, TagType)> {
+        let mut items: Vec<(&TagItem, TagType)> = Vec::new();
+
+        for tag in self.blocks() {
+            for item in tag.items() {
+                if !items.iter().any(|(i, _)| i.key() == item.key()) {
+                    items.push((item, tag.tag_type()));
+                }
+            }
+        }
+
+        items
+    }
+
+    /// Reads a value by its native key in the highest priority tag block
+    /// that contains it. Keys are matched case-insensitively. See the README
+    /// for the key syntax of each format.
+    pub(crate) fn get_raw(&self, key: &str) -> Option<&str> {
+        self.blocks().find_map(|tag| {
+            tag.items().find_map(|item| {
+                let native_key = item.key().map_key(tag.tag_type(), true)?;
+
+                if raw_key_matches(tag.tag_type(), native_key, key) {
+                    item.value().text()
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Returns every value of `key` in the highest priority tag block that
+    /// contains it, split on the configured separators.
+    pub(crate) fn get_all<'a>(&'a self, key: &'a ItemKey) -> Vec<&'a str> {
+        let values: Vec<&str> = self
+            .blocks()
+            .map(|tag| tag.get_strings(key).collect::<Vec<&str>>())
+            .find(|values| !values.is_empty())
+            .unwrap_or_default();
+
+        values
+            .into_iter()
+            .flat_map(|value| split_value(value, &self.separators))
+            .collect()
+    }
+
+    /// Returns every value of the first of `keys` found in the highest
+    /// priority tag block that contains any of them, split on the configured
+    /// separators.
+    pub(crate) fn get_all_raw(&self, keys: &[&str]) -> Vec<&str> {
+        let values: Vec<&str> = self
+            .blocks()
+            .find_map(|tag| {
+                keys.iter()
+                    .map(|key| raw_values(tag, key))
+                    .find(|values| !values.is_empty())
+            })
+            .unwrap_or_default();
+
+        values
+            .into_iter()
+            .flat_map(|value| split_value(value, &self.separators))
+            .collect()
+    }
+
+    /// Returns the pictures in the highest priority tag block that has any.
+    pub(crate) fn pictures(&self) -> &[Picture] {
+        self.tags
+            .iter()
+            .map(Tag::pictures)
+            .find(|pictures| !pictures.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Returns all tag blocks in the file, ordered by priority.
+    pub(crate) fn tag_blocks(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Returns the tag block that new values are written to.
+    pub(crate) fn primary_tag_type(&self) -> TagType {
+        self.format.file_type().primary_tag_type()
+    }
+
+    /// Returns every value of `key` in the tag block of `tag_type`, as
+    /// stored.
+    pub(crate) fn tag_values(
+        &self,
+        tag_type: TagType,
+        key: &ItemKey,
+    ) -> Vec<String> {
+        self.tags
+            .iter()
+            .find(|tag| tag.tag_type() == tag_type)
+            .map(|tag| tag.get_strings(key).map(str::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the values read from the path, followed by all tag blocks.
+    fn blocks(&self) -> impl Iterator<Item = &Tag> {
+        self.overrides.iter().chain(&self.tags)
+    }
+
+    fn get_string(&self, key: &ItemKey) -> Option<&str> {
+        self.blocks().find_map(|tag| tag.get_string(key))
+    }
+
+    /// Reads `key`, falling back on native keys lofty doesn't map to it.
+    fn get_string_or_raw(
+        &self,
+        key: &ItemKey,
+        raw_keys: &[&str],
+    ) -> Option<&str> {
+        self.get_string(key).or_else(|| self.get_raw_any(raw_keys))
+    }
+
+    fn get_raw_any(&self, raw_keys: &[&str]) -> Option<&str> {
+        raw_keys.iter().find_map(|key| self.get_raw(key))
+    }
+}
+
+fn split_value<'a>(value: &'a str, separators: &[String]) -> Vec<&'a str> {
+    let mut parts = vec![value];
+
+    for separator in separators {
+        parts = parts
+            .into_iter()
+            .flat_map(|part| part.split(separator.as_str()))
+            .collect();
+    }
+
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Returns every value in `tag` stored under the native key `key`.
+fn raw_values<'a>(tag: &'a Tag, key: &str) -> Vec<&'a str> {
+    tag.items()
+        .filter(|item| {
+            item.key()
+                .map_key(tag.tag_type(), true)
+                .is_some_and(|native_key| {
+                    raw_key_matches(tag.tag_type(), native_key, key)
+                })
+        })
+        .filter_map(|item| item.value().text())
+        .collect()
+}
+
+fn raw_key_matches(tag_type: TagType, native_key: &str, key: &str) -> bool {
+    native_key.eq_ignore_ascii_case(parse_raw_key(tag_type, key))
+}
+
+/// Returns the native key that lofty uses for the raw `key`. lofty
+/// identifies unmapped `ID3v2` user text frames by their description, so
+/// "TXXX:<description>" becomes the description.
+pub(crate) fn parse_raw_key(tag_type: TagType, key: &str) -> &str {
+    if tag_type == TagType::Id3v2 {
+        key.strip_prefix("TXXX:").unwrap_or(key)
+    } else {
+        key
     }
 }
 
 impl Tags for AudioFile {
     fn album(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::AlbumTitle)
+        self.get_string(&ItemKey::AlbumTitle)
     }
 
     fn album_artist(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::AlbumArtist)
+        self.get_string(&ItemKey::AlbumArtist)
     }
 
     fn albumsort(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::AlbumTitleSortOrder)
+        self.get_string(&ItemKey::AlbumTitleSortOrder)
     }
 
     fn artist(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::TrackArtist)
+        self.get_string(&ItemKey::TrackArtist)
     }
 
     fn genre(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::Genre)
+        self.get_string(&ItemKey::Genre)
     }
 
     fn title(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::TrackTitle)
+        self.get_string(&ItemKey::TrackTitle)
     }
 
     fn raw_disc_number(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::DiscNumber)
+        self.get_string(&ItemKey::DiscNumber)
     }
 
     fn raw_track_number(&self) -> Option<&str> {
-        self.tag.get_string(&ItemKey::TrackNumber)
+        self.get_string(&ItemKey::TrackNumber)
+    }
+
+    // ID3v2 and APE store totals as "x/y". MP4 stores them in a single
+    // `trkn`/`disk` atom, which lofty splits into separate items. Vorbis
+    // comments use separate TRACKTOTAL/DISCTOTAL fields, or the less common
+    // TOTALTRACKS/TOTALDISCS.
+    fn total_track_number(&self) -> Option<&str> {
+        self.raw_track_number()
+            .and_then(|s| self.get_total(s))
+            .or_else(|| {
+                self.get_string_or_raw(
+                    &ItemKey::TrackTotal,
+                    &["TRACKTOTAL", "TOTALTRACKS"],
+                )
+            })
+    }
+
+    fn total_disc_number(&self) -> Option<&str> {
+        self.raw_disc_number()
+            .and_then(|s| self.get_total(s))
+            .or_else(|| {
+                self.get_string_or_raw(
+                    &ItemKey::DiscTotal,
+                    &["DISCTOTAL", "TOTALDISCS"],
+                )
+            })
     }
 
     fn year(&self) -> Option<&str> {
-        self.tag
-            .get_string(&ItemKey::RecordingDate)
-            .or_else(|| self.tag.get_string(&ItemKey::Year))
-            .or_else(|| self.tag.get_string(&ItemKey::OriginalReleaseDate))
+        self.get_string(&ItemKey::RecordingDate)
+            .or_else(|| self.get_string(&ItemKey::Year))
+            .or_else(|| self.get_string(&ItemKey::OriginalReleaseDate))
+    }
+
+    fn artistsort(&self) -> Option<&str> {
+        self.get_string(&ItemKey::TrackArtistSortOrder)
+    }
+
+    fn albumartistsort(&self) -> Option<&str> {
+        self.get_string(&ItemKey::AlbumArtistSortOrder)
+    }
+
+    fn titlesort(&self) -> Option<&str> {
+        self.get_string(&ItemKey::TrackTitleSortOrder)
+    }
+
+    fn composer(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Composer)
+    }
+
+    fn conductor(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Conductor)
+    }
+
+    fn performer(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Performer)
+    }
+
+    fn label(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Label)
+            .or_else(|| self.get_string(&ItemKey::Publisher))
+    }
+
+    fn catalog_number(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::CatalogNumber,
+            &["TXXX:CATALOGNUMBER", "----:com.apple.iTunes:CATALOGNUMBER"],
+        )
+    }
+
+    fn isrc(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Isrc)
+    }
+
+    fn barcode(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::Barcode,
+            &["TXXX:BARCODE", "----:com.apple.iTunes:BARCODE"],
+        )
+    }
+
+    fn bpm(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Bpm)
+    }
+
+    fn initial_key(&self) -> Option<&str> {
+        self.get_string(&ItemKey::InitialKey)
+    }
+
+    fn comment(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Comment)
+    }
+
+    fn release_type(&self) -> Option<&str> {
+        self.get_raw_any(&RELEASE_TYPE_KEYS)
+    }
+
+    fn country(&self) -> Option<&str> {
+        self.get_raw_any(&COUNTRY_KEYS)
+    }
+
+    fn work(&self) -> Option<&str> {
+        self.get_string_or_raw(&ItemKey::Work, &["TXXX:WORK", "©wrk"])
+    }
+
+    fn movement(&self) -> Option<&str> {
+        self.get_string(&ItemKey::Movement)
+    }
+
+    fn movement_number(&self) -> Option<&str> {
+        self.get_string(&ItemKey::MovementNumber)
+    }
+
+    fn raw_compilation(&self) -> Option<&str> {
+        self.get_string(&ItemKey::FlagCompilation)
+    }
+
+    // Picard's "Track Id" is the recording, its "Release Track Id" is the
+    // track.
+    fn musicbrainz_album_id(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::MusicBrainzReleaseId,
+            &[
+                "TXXX:MusicBrainz Album Id",
+                "MUSICBRAINZ_ALBUMID",
+                "----:com.apple.iTunes:MusicBrainz Album Id",
+            ],
+        )
+    }
+
+    fn musicbrainz_release_group_id(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::MusicBrainzReleaseGroupId,
+            &[
+                "TXXX:MusicBrainz Release Group Id",
+                "MUSICBRAINZ_RELEASEGROUPID",
+                "----:com.apple.iTunes:MusicBrainz Release Group Id",
+            ],
+        )
+    }
+
+    fn musicbrainz_artist_id(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::MusicBrainzArtistId,
+            &[
+                "TXXX:MusicBrainz Artist Id",
+                "MUSICBRAINZ_ARTISTID",
+                "----:com.apple.iTunes:MusicBrainz Artist Id",
+            ],
+        )
+    }
+
+    fn musicbrainz_track_id(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::MusicBrainzRecordingId,
+            &[
+                "TXXX:MusicBrainz Track Id",
+                "MUSICBRAINZ_TRACKID",
+                "----:com.apple.iTunes:MusicBrainz Track Id",
+            ],
+        )
+    }
+
+    fn musicbrainz_release_track_id(&self) -> Option<&str> {
+        self.get_string_or_raw(
+            &ItemKey::MusicBrainzTrackId,
+            &[
+                "TXXX:MusicBrainz Release Track Id",
+                "MUSICBRAINZ_RELEASETRACKID",
+                "----:com.apple.iTunes:MusicBrainz Release Track Id",
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+impl AudioFile {
+    /// Creates an `AudioFile` from tag blocks in memory, ordered by
+    /// priority. The format is determined by the extension of `path`.
+    pub(crate) fn from_tags(path: &Path, tags: Vec<Tag>) -> AudioFile {
+        let extension =
+            path.extension().map(|e| e.to_string_lossy().to_string());
+
+        AudioFile {
+            path: path.to_owned(),
+            tags,
+            overrides: None,
+            properties: FileProperties::default(),
+            format: Format::from_file_type(
+                &FileType::from_ext(extension.as_deref().unwrap()).unwrap(),
+            )
+            .unwrap(),
+            extension,
+            separators: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use lofty::ItemValue;
+
+    fn audiofile_from_tags(tags: Vec<Tag>) -> AudioFile {
+        AudioFile::from_tags(Path::new("test.m4a"), tags)
+    }
+
+    #[test]
+    fn test_mp4_number_pairs() {
+        let mut tag = Tag::new(TagType::Mp4Ilst);
+        tag.insert_text(ItemKey::TrackNumber, "5".to_owned());
+        tag.insert_text(ItemKey::TrackTotal, "12".to_owned());
+        tag.insert_text(ItemKey::DiscNumber, "1".to_owned());
+        tag.insert_text(ItemKey::DiscTotal, "2".to_owned());
+
+        let audiofile = audiofile_from_tags(vec![tag]);
+
+        assert_eq!(audiofile.track_number(), Some("5"));
+        assert_eq!(audiofile.total_track_number(), Some("12"));
+        assert_eq!(audiofile.disc_number(), Some("1"));
+        assert_eq!(audiofile.total_disc_number(), Some("2"));
+    }
+
+    #[test]
+    fn test_number_pair_strings() {
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.insert_text(ItemKey::TrackNumber, "5/12".to_owned());
+
+        let audiofile = audiofile_from_tags(vec![tag]);
+
+        assert_eq!(audiofile.track_number(), Some("5"));
+        assert_eq!(audiofile.total_track_number(), Some("12"));
+        assert_eq!(audiofile.disc_number(), None);
+        assert_eq!(audiofile.total_disc_number(), None);
+    }
+
+    #[test]
+    fn test_vorbis_totals() {
+        let mut vorbis = Tag::new(TagType::VorbisComments);
+        vorbis.insert_text(ItemKey::TrackNumber, "5".to_owned());
+        vorbis.insert_text(ItemKey::TrackTotal, "12".to_owned());
+        vorbis.insert_text(ItemKey::DiscNumber, "1".to_owned());
+        vorbis.insert_unchecked(TagItem::new(
+            ItemKey::Unknown("TOTALDISCS".to_owned()),
+            ItemValue::Text("2".to_owned()),
+        ));
+
+        let audiofile = audiofile_from_tags(vec![vorbis]);
+
+        assert_eq!(audiofile.total_track_number(), Some("12"));
+        assert_eq!(audiofile.total_disc_number(), Some("2"));
+    }
+
+    #[test]
+    fn test_merged_tags() {
+        let mut id3v2 = Tag::new(TagType::Id3v2);
+        id3v2.insert_text(ItemKey::AlbumTitle, "Album".to_owned());
+
+        let mut id3v1 = Tag::new(TagType::Id3v1);
+        id3v1.insert_text(ItemKey::AlbumTitle, "Truncated Alb".to_owned());
+        id3v1.insert_text(ItemKey::TrackArtist, "Artist".to_owned());
+
+        let audiofile = audiofile_from_tags(vec![id3v2, id3v1]);
+
+        assert_eq!(audiofile.album(), Some("Album"));
+        assert_eq!(audiofile.artist(), Some("Artist"));
+
+        let sources: Vec<TagType> = audiofile
+            .merged_items()
+            .into_iter()
+            .map(|(_, tag_type)| tag_type)
+            .collect();
+
+        assert_eq!(sources, [TagType::Id3v2, TagType::Id3v1]);
+    }
+
+    #[test]
+    fn test_raw_tags() {
+        let mut vorbis = Tag::new(TagType::VorbisComments);
+        vorbis.insert_unchecked(TagItem::new(
+            ItemKey::Unknown("RELEASETYPE".to_owned()),
+            ItemValue::Text("album".to_owned()),
+        ));
+
+        let audiofile = audiofile_from_tags(vec![vorbis]);
+
+        assert_eq!(audiofile.get_raw("RELEASETYPE"), Some("album"));
+        assert_eq!(audiofile.get_raw("releasetype"), Some("album"));
+        assert_eq!(audiofile.get_raw("CATALOGNUMBER"), None);
+    }
+
+    #[test]
+    fn test_raw_key_matches() {
+        assert!(raw_key_matches(TagType::Id3v2, "TPE1", "TPE1"));
+        assert!(raw_key_matches(
+            TagType::Id3v2,
+            "CATALOGNUMBER",
+            "TXXX:CATALOGNUMBER"
+        ));
+        assert!(!raw_key_matches(
+            TagType::VorbisComments,
+            "CATALOGNUMBER",
+            "TXXX:CATALOGNUMBER"
+        ));
+        assert!(raw_key_matches(
+            TagType::Mp4Ilst,
+            "----:com.apple.iTunes:MusicBrainz Album Type",
+            "----:com.apple.iTunes:MusicBrainz Album Type"
+        ));
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let mut vorbis = Tag::new(TagType::VorbisComments);
+        vorbis.push_unchecked(TagItem::new(
+            ItemKey::TrackArtist,
+            ItemValue::Text("Artist A".to_owned()),
+        ));
+        vorbis.push_unchecked(TagItem::new(
+            ItemKey::TrackArtist,
+            ItemValue::Text("Artist B; Artist C".to_owned()),
+        ));
+
+        let mut audiofile = audiofile_from_tags(vec![vorbis]);
+
+        assert_eq!(
+            audiofile.get_all(&ItemKey::TrackArtist),
+            ["Artist A", "Artist B; Artist C"]
+        );
+
+        audiofile.separators = vec!["; ".to_owned()];
+
+        assert_eq!(
+            audiofile.get_all(&ItemKey::TrackArtist),
+            ["Artist A", "Artist B", "Artist C"]
+        );
+
+        assert!(audiofile.get_all(&ItemKey::Genre).is_empty());
+    }
+
+    #[test]
+    fn test_split_value() {
+        let separators =
+            ["; ".to_owned(), " / ".to_owned(), "feat.".to_owned()];
+
+        assert_eq!(
+            split_value("A; B / C feat. D", &separators),
+            ["A", "B", "C", "D"]
+        );
+        assert_eq!(split_value("A", &separators), ["A"]);
+        assert_eq!(split_value("A", &[]), ["A"]);
+    }
+
+    #[test]
+    fn test_extended_tags() {
+        let mut id3v2 = Tag::new(TagType::Id3v2);
+        id3v2.insert_text(ItemKey::Composer, "Composer".to_owned());
+        id3v2.insert_text(ItemKey::Publisher, "Publisher".to_owned());
+        id3v2.insert_text(ItemKey::FlagCompilation, "1".to_owned());
+        id3v2.insert_unchecked(TagItem::new(
+            ItemKey::Unknown("MusicBrainz Album Type".to_owned()),
+            ItemValue::Text("album".to_owned()),
+        ));
+
+        let audiofile = audiofile_from_tags(vec![id3v2]);
+
+        assert_eq!(audiofile.composer(), Some("Composer"));
+        assert_eq!(audiofile.label(), Some("Publisher"));
+        assert_eq!(audiofile.release_type(), Some("album"));
+        assert_eq!(audiofile.country(), None);
+        assert!(audiofile.compilation());
+    }
+
+    #[test]
+    fn test_ape_release_keys() {
+        let mut ape = Tag::new(TagType::Ape);
+        ape.insert_unchecked(TagItem::new(
+            ItemKey::Unknown("MUSICBRAINZ_ALBUMTYPE".to_owned()),
+            ItemValue::Text("album; compilation".to_owned()),
+        ));
+        ape.insert_unchecked(TagItem::new(
+            ItemKey::Unknown("RELEASECOUNTRY".to_owned()),
+            ItemValue::Text("NL".to_owned()),
+        ));
+
+        let mut audiofile = audiofile_from_tags(vec![ape]);
+        audiofile.separators = vec!["; ".to_owned()];
+
+        assert_eq!(audiofile.release_type(), Some("album; compilation"));
+        assert_eq!(audiofile.country(), Some("NL"));
+
+        let keys = raw_keys_from_keyword("release_type").unwrap();
+        assert_eq!(audiofile.get_all_raw(keys), ["album", "compilation"]);
+
+        let keys = raw_keys_from_keyword("country").unwrap();
+        assert_eq!(audiofile.get_all_raw(keys), ["NL"]);
+    }
+
+    #[test]
+    fn test_musicbrainz_ids() {
+        let mut mp4 = Tag::new(TagType::Mp4Ilst);
+        mp4.insert_unchecked(TagItem::new(
+            ItemKey::Unknown(
+                "----:com.apple.iTunes:MusicBrainz Album Id".to_owned(),
+            ),
+            ItemValue::Text("album-id".to_owned()),
+        ));
+
+        let mut vorbis = Tag::new(TagType::VorbisComments);
+        vorbis.insert_unchecked(TagItem::new(
+            ItemKey::Unknown("MUSICBRAINZ_RELEASEGROUPID".to_owned()),
+            ItemValue::Text("release-group-id".to_owned()),
+        ));
+
+        let audiofile = audiofile_from_tags(vec![mp4, vorbis]);
+
+        assert_eq!(audiofile.musicbrainz_album_id(), Some("album-id"));
+        assert_eq!(
+            audiofile.musicbrainz_release_group_id(),
+            Some("release-group-id")
+        );
+        assert_eq!(audiofile.musicbrainz_track_id(), None);
+    }
+
+    #[test]
+    fn test_probe() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let source = Path::new(
+            "tests/testdata/music/Virgo Serena - Damjan Mravunac.flac",
+        );
+
+        let mislabeled = tempdir.path().join("mislabeled.mp3");
+        std::fs::copy(source, &mislabeled)?;
+
+        let format = AudioFile::probe(&mislabeled);
+        assert_eq!(format.map(Format::name), Some("FLAC"));
+
+        let uppercase = tempdir.path().join("uppercase.FLAC");
+        std::fs::copy(source, &uppercase)?;
+
+        let format = AudioFile::probe(&uppercase);
+        assert_eq!(format.map(Format::canonical_extension), Some("flac"));
+
+        let text = tempdir.path().join("text.txt");
+        std::fs::write(&text, "Not an audio file.")?;
+
+        assert!(AudioFile::probe(&text).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_audio() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let source = Path::new(
+            "tests/testdata/music/Virgo Serena - Damjan Mravunac.flac",
+        );
+
+        // Probing falls back on audio extensions, so unreadable files are
+        // still gathered and reported.
+        let text = tempdir.path().join("text.MP3");
+        std::fs::write(&text, "Not an audio file.")?;
+        assert!(AudioFile::is_audio(&text));
+
+        // Known non-audio extensions are never read.
+        let image = tempdir.path().join("cover.jpg");
+        std::fs::copy(source, &image)?;
+        assert!(!AudioFile::is_audio(&image));
+
+        // Files without a known extension are recognized by content.
+        let no_extension = tempdir.path().join("track");
+        std::fs::copy(source, &no_extension)?;
+        assert!(AudioFile::is_audio(&no_extension));
+
+        let unknown = tempdir.path().join("readme.rtf");
+        std::fs::write(&unknown, "Not an audio file.")?;
+        assert!(!AudioFile::is_audio(&unknown));
+
+        // Removed files are no longer audio files.
+        std::fs::remove_file(&no_extension)?;
+        assert!(!AudioFile::is_audio(&no_extension));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_path_values() -> Result<()> {
+        let tempdir = TempDir::new()?;
+
+        let source =
+            Path::new("tests/testdata/music/Under Siege - Amon Amarth.mp3");
+
+        let path = tempdir.path().join("Amon Amarth").join("Johan Hegg.mp3");
+        std::fs::create_dir(tempdir.path().join("Amon Amarth"))?;
+        std::fs::copy(source, &path)?;
+
+        // lofty can't map performers to ID3v2 frames.
+        let pattern = PathPattern::new("{artist}/{performer}")?;
+        let options = TagOptions::new(Vec::new(), Vec::new(), Some(pattern));
+
+        assert!(AudioFile::new(&path, &options).is_err());
+
+        let pattern = PathPattern::new("{artist}/{title}")?;
+        let options = TagOptions::new(Vec::new(), Vec::new(), Some(pattern));
+
+        let audiofile = AudioFile::new(&path, &options)?;
+        assert_eq!(audiofile.title(), Some("Johan Hegg"));
+
+        Ok(())
     }
 }
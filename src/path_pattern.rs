@@ -0,0 +1,167 @@
+use crate::file::item_key_from_keyword;
+use anyhow::{anyhow, bail, Result};
+use lofty::ItemKey;
+use regex::Regex;
+use std::fmt::Write;
+use std::path::{Component, Path};
+
+/// Extracts tag values from the path of a file, e.g. with
+/// `{artist}/{album}/{track} - {title}`.
+#[derive(Debug, Clone)]
+pub(crate) struct PathPattern {
+    regex: Regex,
+}
+
+impl PathPattern {
+    /// Creates a pattern from a string with `{field}` placeholders. All other
+    /// characters match literally, and `/` matches a path separator.
+    pub(crate) fn new(pattern: &str) -> Result<Self> {
+        let mut regex = String::from("(?:^|/)");
+
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                bail!("Unclosed '{{' in pattern \"{pattern}\"")
+            };
+
+            regex.push_str(&regex::escape(&rest[..start]));
+            // Writing to a String can't fail.
+            let _ = write!(
+                regex,
+                "(?P<{}>[^/]+?)",
+                &rest[start + 1..start + length]
+            );
+
+            rest = &rest[start + length + 1..];
+        }
+
+        regex.push_str(&regex::escape(rest));
+        regex.push('$');
+
+        Self::from_regex(&regex)
+    }
+
+    /// Creates a pattern from a regular expression. The names of its groups
+    /// are fields.
+    pub(crate) fn from_regex(regex: &str) -> Result<Self> {
+        let regex = Regex::new(regex)?;
+
+        for name in regex.capture_names().flatten() {
+            item_key_from_field(name).ok_or_else(|| {
+                anyhow!("Unknown field \"{name}\" in pattern")
+            })?;
+        }
+
+        Ok(PathPattern { regex })
+    }
+
+    /// Parses `pattern` as a regular expression if `is_regex` is true.
+    pub(crate) fn parse(pattern: &str, is_regex: bool) -> Result<Self> {
+        if is_regex {
+            Self::from_regex(pattern)
+        } else {
+            Self::new(pattern)
+        }
+    }
+
+    /// Matches the path of a file, without its extension and with `/` as
+    /// separator, against the pattern. Returns `None` if it doesn't match.
+    pub(crate) fn captures(
+        &self,
+        path: &Path,
+    ) -> Option<Vec<(ItemKey, String)>> {
+        let path = path
+            .with_extension("")
+            .components()
+            .filter_map(|component| {
+                if let Component::Normal(name) = component {
+                    Some(name.to_string_lossy().to_string())
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("/");
+
+        let captures = self.regex.captures(&path)?;
+
+        let values = self
+            .regex
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                let value = captures.name(name)?.as_str().trim();
+
+                if value.is_empty() {
+                    None
+                } else {
+                    Some((item_key_from_field(name)?, value.to_owned()))
+                }
+            })
+            .collect();
+
+        Some(values)
+    }
+}
+
+/// Fields are the keywords of `tags`, plus `track` and `disc`.
+fn item_key_from_field(field: &str) -> Option<ItemKey> {
+    match field {
+        "track" => Some(ItemKey::TrackNumber),
+        "disc" => Some(ItemKey::DiscNumber),
+        _ => item_key_from_keyword(field),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_pattern() -> Result<()> {
+        let pattern = PathPattern::new("{artist}/{album}/{track} - {title}")?;
+
+        let path = PathBuf::from("music")
+            .join("Damjan Mravunac")
+            .join("The Talos Principle")
+            .join("02 - Virgo Serena.flac");
+
+        assert_eq!(
+            pattern.captures(&path),
+            Some(vec![
+                (ItemKey::TrackArtist, "Damjan Mravunac".to_owned()),
+                (ItemKey::AlbumTitle, "The Talos Principle".to_owned()),
+                (ItemKey::TrackNumber, "02".to_owned()),
+                (ItemKey::TrackTitle, "Virgo Serena".to_owned()),
+            ])
+        );
+
+        assert_eq!(pattern.captures(Path::new("Virgo Serena.flac")), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex() -> Result<()> {
+        let pattern =
+            PathPattern::from_regex(r"(?P<track>\d+)\. (?P<title>[^/]+)$")?;
+
+        assert_eq!(
+            pattern.captures(Path::new("Album/2. Virgo Serena.mp3")),
+            Some(vec![
+                (ItemKey::TrackNumber, "2".to_owned()),
+                (ItemKey::TrackTitle, "Virgo Serena".to_owned()),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        assert!(PathPattern::new("{artist} - {nonsense}").is_err());
+        assert!(PathPattern::new("{artist").is_err());
+    }
+}
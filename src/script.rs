@@ -2,15 +2,26 @@ use anyhow::{bail, Result};
 use std::path::Path;
 use tapr::{Environment, Interpreter, Node, NodeData, Value, Visitor};
 
-/// Reads a script, parses an AST and gets the name, description and parameters.
+/// Reads a script, parses an AST and gets the name and the parameters of its
+/// entry points.
 #[derive(Debug)]
 pub struct Script {
     name: String,
-    parameters: Vec<String>,
+    entry_points: Vec<(String, Vec<String>)>,
     node: Node,
 }
 
 impl Script {
+    /// Entry point of scripts used by `rename`.
+    pub const RENAME: &'static str = "rename";
+    /// Entry point of scripts used by `retag`.
+    pub const RETAG: &'static str = "retag";
+    /// Entry point of scripts used by `playlist`.
+    pub const PLAYLIST: &'static str = "playlist";
+
+    const ENTRY_POINTS: [&'static str; 3] =
+        [Self::RENAME, Self::RETAG, Self::PLAYLIST];
+
     /// Create a new Script instance.
     pub fn from_file(path: &Path) -> Result<Self> {
         let name = path
@@ -20,7 +31,9 @@ impl Script {
 
         let body = std::fs::read_to_string(path)?;
 
-        let node = Node::from_string(&body, &name)?;
+        let mut node = Node::from_string(&body, &name)?;
+
+        crate::tapr::check_properties(&mut node)?;
 
         let mut intp = Interpreter::default();
         intp.push_environment(Environment::new());
@@ -29,23 +42,34 @@ impl Script {
 
         let env: Environment = intp.pop_environment();
 
-        let Some(value) = env.get("rename") else {
-            bail!("Script did not include rename function.")
-        };
-
-        let Value::Callable(callable) = value else {
-            bail!("Script did not include rename function.")
-        };
-
-        let parameters = callable
-            .parameters()
+        let entry_points: Vec<(String, Vec<String>)> = Self::ENTRY_POINTS
             .iter()
-            .map(|p| p.name().to_owned())
+            .filter_map(|entry_point| {
+                let Some(Value::Callable(callable)) = env.get(entry_point)
+                else {
+                    return None;
+                };
+
+                let parameters = callable
+                    .parameters()
+                    .iter()
+                    .map(|p| p.name().to_owned())
+                    .collect();
+
+                Some(((*entry_point).to_owned(), parameters))
+            })
             .collect();
 
+        if entry_points.is_empty() {
+            bail!(
+                "Script did not include any of these functions: {}",
+                Self::ENTRY_POINTS.join(", ")
+            )
+        }
+
         Ok(Script {
             name: name.to_string(),
-            parameters,
+            entry_points,
             node,
         })
     }
@@ -55,9 +79,17 @@ impl Script {
         &self.name
     }
 
-    /// Returns the parameters
-    pub fn parameters(&self) -> &[String] {
-        &self.parameters
+    /// Returns the names and parameters of the entry points
+    pub fn entry_points(&self) -> &[(String, Vec<String>)] {
+        &self.entry_points
+    }
+
+    /// Returns the parameters of `entry_point`, if the script defines it.
+    pub fn parameters(&self, entry_point: &str) -> Option<&[String]> {
+        self.entry_points
+            .iter()
+            .find(|(name, _)| name == entry_point)
+            .map(|(_, parameters)| parameters.as_slice())
     }
 
     /// Accepts a visitor
@@ -70,12 +102,21 @@ impl Script {
 
     pub fn add_arguments_to_node(
         &mut self,
+        entry_point: &str,
         arguments: &[String],
     ) -> Result<()> {
-        if self.parameters.len() != arguments.len() {
+        let Some(parameters) = self.parameters(entry_point) else {
+            bail!(
+                "Script '{}' does not include a {} function.",
+                self.name,
+                entry_point
+            )
+        };
+
+        if parameters.len() != arguments.len() {
             bail!(
                 "Script expected {} args, received {}",
-                self.parameters.len(),
+                parameters.len(),
                 arguments.len()
             )
         }
@@ -84,15 +125,15 @@ impl Script {
             panic!("Root node of script '{}' is not Node::main", self.name);
         };
 
-        main_nodes.push(Self::create_mock_node(arguments));
+        main_nodes.push(Self::create_mock_node(entry_point, arguments));
 
         Ok(())
     }
 
-    fn create_mock_node(arguments: &[String]) -> Node {
+    fn create_mock_node(entry_point: &str, arguments: &[String]) -> Node {
         let mut nodes = vec![Node::mock(NodeData::Symbol {
             module: None,
-            value: "rename".to_owned(),
+            value: entry_point.to_owned(),
         })];
 
         nodes.extend(
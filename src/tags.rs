@@ -21,11 +21,89 @@ pub trait Tags: std::fmt::Debug + Send + Sync {
     /// The current `[AudioFile]`s year, if any.
     fn year(&self) -> Option<&str>;
 
+    /// The current `[AudioFile]`s artistsort, if any.
+    fn artistsort(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s albumartistsort, if any.
+    fn albumartistsort(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s titlesort, if any.
+    fn titlesort(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s composer, if any.
+    fn composer(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s conductor, if any.
+    fn conductor(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s performer, if any.
+    fn performer(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s label, if any.
+    fn label(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s catalog number, if any.
+    fn catalog_number(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s ISRC, if any.
+    fn isrc(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s barcode, if any.
+    fn barcode(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s BPM, if any.
+    fn bpm(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s initial key, if any.
+    fn initial_key(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s comment, if any.
+    fn comment(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s release type, if any.
+    fn release_type(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s release country, if any.
+    fn country(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s work, if any.
+    fn work(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s movement, if any.
+    fn movement(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s movement number, if any.
+    fn movement_number(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s raw compilation flag, if any.
+    fn raw_compilation(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s `MusicBrainz` Album Id, if any.
+    fn musicbrainz_album_id(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s `MusicBrainz` Release Group Id, if any.
+    fn musicbrainz_release_group_id(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s `MusicBrainz` Artist Id, if any.
+    fn musicbrainz_artist_id(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s `MusicBrainz` Track Id, if any.
+    fn musicbrainz_track_id(&self) -> Option<&str>;
+
+    /// The current `[AudioFile]`s `MusicBrainz` Release Track Id, if any.
+    fn musicbrainz_release_track_id(&self) -> Option<&str>;
+
     /// The current `[AudioFile]`s date, if any.
     fn date(&self) -> Option<&str> {
         self.year()
     }
 
+    /// Whether the current `[AudioFile]` is part of a compilation.
+    fn compilation(&self) -> bool {
+        self.raw_compilation()
+            .is_some_and(|s| s == "1" || s.eq_ignore_ascii_case("true"))
+    }
+
     /// The current `[AudioFile]`s track number, if any.
     fn track_number(&self) -> Option<&str> {
         self.raw_track_number().map(|s| self.get_current(s))
@@ -1,22 +1,102 @@
 #![allow(clippy::needless_pass_by_value)]
 #![allow(clippy::unnecessary_wraps)]
 
-use crate::{file::AudioFile, tags::Tags};
+use crate::file::{item_key_from_keyword, raw_keys_from_keyword, AudioFile};
+use crate::script::Script;
+use crate::tags::Tags;
+use anyhow::{bail, Result};
 use conv::ConvAsUtil;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use tapr::{
     Arguments, Callable, CallableType, Environment, Interpreter,
-    NativeFunction, Parameters, TaprErrorKind, TaprResult, Value,
+    NativeFunction, Node, NodeData, Parameters, TaprErrorKind, TaprResult,
+    Value,
 };
 
-pub(crate) fn get_tapr_environment(audiofile: AudioFile) -> Environment {
+/// Holds an error raised by a native function that tapr can't express, e.g.
+/// an unknown property. The first error is kept.
+#[derive(Clone, Default)]
+struct Failure(Arc<Mutex<Option<anyhow::Error>>>);
+
+impl Failure {
+    fn set(&self, error: anyhow::Error) {
+        let mut failure = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        failure.get_or_insert(error);
+    }
+
+    fn take(&self) -> Option<anyhow::Error> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+}
+
+/// Runs `script` for `audiofile` and returns its value.
+pub(crate) fn run_script(
+    script: &Script,
+    audiofile: Arc<AudioFile>,
+) -> Result<Value> {
+    evaluate(audiofile, |intp| script.accept(intp))
+}
+
+fn evaluate<F>(audiofile: Arc<AudioFile>, accept: F) -> Result<Value>
+where
+    F: FnOnce(&mut Interpreter) -> TaprResult<Value>,
+{
+    let failure = Failure::default();
+
+    let mut intp = Interpreter::default();
+    intp.push_environment(get_tapr_environment(audiofile, &failure));
+
+    let value = accept(&mut intp)?;
+
+    match failure.take() {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
+
+fn get_tapr_environment(
+    audiofile: Arc<AudioFile>,
+    failure: &Failure,
+) -> Environment {
     let mut env = Environment::new();
 
     let tag_function =
-        Value::Callable(Arc::new(TagsFunction(Box::new(audiofile))));
+        Value::Callable(Arc::new(TagsFunction(audiofile.clone())));
 
     env.insert("tags".to_owned(), tag_function).unwrap();
 
+    let props_function = Value::Callable(Arc::new(PropertiesFunction(
+        audiofile.clone(),
+        failure.clone(),
+    )));
+
+    env.insert("props".to_owned(), props_function).unwrap();
+
+    env.insert(
+        "tags-all".to_owned(),
+        Value::Callable(Arc::new(TagsAllFunction(audiofile.clone()))),
+    )
+    .unwrap();
+
+    env.insert(
+        "tag-raw".to_owned(),
+        Value::Callable(Arc::new(TagRawFunction(audiofile.clone()))),
+    )
+    .unwrap();
+
+    env.insert(
+        "has-cover".to_owned(),
+        Value::Callable(Arc::new(HasCoverFunction(audiofile.clone()))),
+    )
+    .unwrap();
+
+    env.insert(
+        "cover-count".to_owned(),
+        Value::Callable(Arc::new(CoverCountFunction(audiofile))),
+    )
+    .unwrap();
+
     env.insert(
         "zero-pad".to_owned(),
         NativeFunction::new(
@@ -42,7 +122,7 @@ pub(crate) fn get_tapr_environment(audiofile: AudioFile) -> Environment {
     env
 }
 
-struct TagsFunction(pub Box<dyn Tags>);
+struct TagsFunction(pub Arc<dyn Tags>);
 
 impl Callable for TagsFunction {
     fn call(
@@ -52,25 +132,7 @@ impl Callable for TagsFunction {
     ) -> TaprResult<Value> {
         let keyword = arguments.unwrap_keyword(0);
 
-        let string = match keyword.as_str() {
-            "album" => self.0.album().unwrap_or(""),
-            "album_artist" | "albumartist" => {
-                self.0.album_artist().unwrap_or("")
-            }
-            "album_sort" | "albumsort" => self.0.albumsort().unwrap_or(""),
-            "artist" => self.0.artist().unwrap_or(""),
-            "genre" => self.0.genre().unwrap_or(""),
-            "title" => self.0.title().unwrap_or(""),
-            "year" => self.0.year().unwrap_or(""),
-            "date" => self.0.date().unwrap_or(""),
-            "track_number" | "tracknumber" => {
-                self.0.track_number().unwrap_or("")
-            }
-            "disc_number" | "discnumber" | "disk_number" | "disknumber" => {
-                self.0.disc_number().unwrap_or("")
-            }
-            _ => "",
-        };
+        let string = tag_value(&*self.0, &keyword);
 
         Ok(if string.is_empty() { Value::Nil } else { string.trim().into() })
     }
@@ -88,6 +150,330 @@ impl Callable for TagsFunction {
     }
 }
 
+fn tag_value<'a>(tags: &'a dyn Tags, keyword: &str) -> &'a str {
+    match keyword {
+        "album" => tags.album().unwrap_or(""),
+        "album_artist" | "albumartist" => tags.album_artist().unwrap_or(""),
+        "album_sort" | "albumsort" => tags.albumsort().unwrap_or(""),
+        "artist" => tags.artist().unwrap_or(""),
+        "genre" => tags.genre().unwrap_or(""),
+        "title" => tags.title().unwrap_or(""),
+        "year" => tags.year().unwrap_or(""),
+        "date" => tags.date().unwrap_or(""),
+        "track_number" | "tracknumber" => tags.track_number().unwrap_or(""),
+        "disc_number" | "discnumber" | "disk_number" | "disknumber" => {
+            tags.disc_number().unwrap_or("")
+        }
+        "total_tracks" | "totaltracks" | "track_total" | "tracktotal" => {
+            tags.total_track_number().unwrap_or("")
+        }
+        "total_discs" | "totaldiscs" | "disc_total" | "disctotal" => {
+            tags.total_disc_number().unwrap_or("")
+        }
+        "artist_sort" | "artistsort" => tags.artistsort().unwrap_or(""),
+        "album_artist_sort" | "albumartistsort" => {
+            tags.albumartistsort().unwrap_or("")
+        }
+        "title_sort" | "titlesort" => tags.titlesort().unwrap_or(""),
+        "composer" => tags.composer().unwrap_or(""),
+        "conductor" => tags.conductor().unwrap_or(""),
+        "performer" => tags.performer().unwrap_or(""),
+        "label" => tags.label().unwrap_or(""),
+        "catalog_number" | "catalognumber" => {
+            tags.catalog_number().unwrap_or("")
+        }
+        "isrc" => tags.isrc().unwrap_or(""),
+        "barcode" => tags.barcode().unwrap_or(""),
+        "bpm" => tags.bpm().unwrap_or(""),
+        "key" | "initial_key" | "initialkey" => {
+            tags.initial_key().unwrap_or("")
+        }
+        "comment" => tags.comment().unwrap_or(""),
+        // Not a compilation falls through to the empty default.
+        "compilation" if tags.compilation() => "1",
+        "release_type" | "releasetype" => tags.release_type().unwrap_or(""),
+        "country" | "release_country" | "releasecountry" => {
+            tags.country().unwrap_or("")
+        }
+        "work" => tags.work().unwrap_or(""),
+        "movement" => tags.movement().unwrap_or(""),
+        "movement_number" | "movementnumber" => {
+            tags.movement_number().unwrap_or("")
+        }
+        "musicbrainz_album_id" | "musicbrainz_albumid" => {
+            tags.musicbrainz_album_id().unwrap_or("")
+        }
+        "musicbrainz_release_group_id" | "musicbrainz_releasegroupid" => {
+            tags.musicbrainz_release_group_id().unwrap_or("")
+        }
+        "musicbrainz_artist_id" | "musicbrainz_artistid" => {
+            tags.musicbrainz_artist_id().unwrap_or("")
+        }
+        "musicbrainz_track_id" | "musicbrainz_trackid" => {
+            tags.musicbrainz_track_id().unwrap_or("")
+        }
+        "musicbrainz_release_track_id" | "musicbrainz_releasetrackid" => {
+            tags.musicbrainz_release_track_id().unwrap_or("")
+        }
+        _ => "",
+    }
+}
+
+struct TagsAllFunction(pub Arc<AudioFile>);
+
+impl Callable for TagsAllFunction {
+    fn call(
+        &self,
+        _: &mut Interpreter,
+        arguments: Arguments,
+    ) -> TaprResult<Value> {
+        let keyword = arguments.unwrap_keyword(0);
+
+        let values = all_tag_values(&self.0, &keyword);
+
+        Ok(if values.is_empty() {
+            Value::Nil
+        } else {
+            Value::List(values.into_iter().map(Value::String).collect())
+        })
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn callable_type(&self) -> CallableType {
+        CallableType::Native
+    }
+
+    fn parameters(&self) -> Parameters {
+        "k:keyword".try_into().unwrap()
+    }
+}
+
+/// Keywords whose value is derived from several keys or from an "x/y"
+/// value. They have a single value, read the same way as `tags` reads them.
+const DERIVED_KEYWORDS: [&str; 16] = [
+    "year",
+    "date",
+    "track_number",
+    "tracknumber",
+    "disc_number",
+    "discnumber",
+    "disk_number",
+    "disknumber",
+    "total_tracks",
+    "totaltracks",
+    "track_total",
+    "tracktotal",
+    "total_discs",
+    "totaldiscs",
+    "disc_total",
+    "disctotal",
+];
+
+fn all_tag_values(audiofile: &AudioFile, keyword: &str) -> Vec<String> {
+    let key = item_key_from_keyword(keyword);
+
+    let values = if DERIVED_KEYWORDS.contains(&keyword) {
+        Some(tag_value(audiofile, keyword).trim())
+            .filter(|value| !value.is_empty())
+            .into_iter()
+            .collect()
+    } else if let Some(key) = &key {
+        audiofile.get_all(key)
+    } else if let Some(keys) = raw_keys_from_keyword(keyword) {
+        audiofile.get_all_raw(keys)
+    } else {
+        Vec::new()
+    };
+
+    values.into_iter().map(str::to_owned).collect()
+}
+
+struct TagRawFunction(pub Arc<AudioFile>);
+
+impl Callable for TagRawFunction {
+    fn call(
+        &self,
+        _: &mut Interpreter,
+        arguments: Arguments,
+    ) -> TaprResult<Value> {
+        let key = arguments.unwrap_string(0);
+
+        let string = self.0.get_raw(&key).unwrap_or("");
+
+        Ok(if string.is_empty() { Value::Nil } else { string.trim().into() })
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn callable_type(&self) -> CallableType {
+        CallableType::Native
+    }
+
+    fn parameters(&self) -> Parameters {
+        "key:string".try_into().unwrap()
+    }
+}
+
+struct HasCoverFunction(pub Arc<AudioFile>);
+
+impl Callable for HasCoverFunction {
+    fn call(&self, _: &mut Interpreter, _: Arguments) -> TaprResult<Value> {
+        Ok((!self.0.pictures().is_empty()).into())
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn callable_type(&self) -> CallableType {
+        CallableType::Native
+    }
+
+    fn parameters(&self) -> Parameters {
+        "".try_into().unwrap()
+    }
+}
+
+struct CoverCountFunction(pub Arc<AudioFile>);
+
+impl Callable for CoverCountFunction {
+    fn call(&self, _: &mut Interpreter, _: Arguments) -> TaprResult<Value> {
+        let count = u32::try_from(self.0.pictures().len()).unwrap_or(u32::MAX);
+
+        Ok(f64::from(count).into())
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn callable_type(&self) -> CallableType {
+        CallableType::Native
+    }
+
+    fn parameters(&self) -> Parameters {
+        "".try_into().unwrap()
+    }
+}
+
+struct PropertiesFunction(pub Arc<AudioFile>, Failure);
+
+impl PropertiesFunction {
+    // Literal keys are checked when the script is loaded, computed keys fail
+    // the script after it has run.
+    fn get(&self, key: &str) -> Value {
+        property(&self.0, key).unwrap_or_else(|error| {
+            self.1.set(error);
+            Value::Nil
+        })
+    }
+}
+
+impl Callable for PropertiesFunction {
+    fn call(
+        &self,
+        _: &mut Interpreter,
+        arguments: Arguments,
+    ) -> TaprResult<Value> {
+        let keyword = arguments.unwrap_keyword(0);
+
+        Ok(self.get(&keyword))
+    }
+
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn callable_type(&self) -> CallableType {
+        CallableType::Native
+    }
+
+    fn parameters(&self) -> Parameters {
+        "k:keyword".try_into().unwrap()
+    }
+}
+
+/// Properties supported by `props`. Underscores and hyphens in keys are
+/// ignored, so `:sample_rate` and `:sample-rate` both work.
+const PROPERTIES: [&str; 8] = [
+    "codec",
+    "lossless",
+    "duration",
+    "bitrate",
+    "audiobitrate",
+    "samplerate",
+    "bitdepth",
+    "channels",
+];
+
+fn normalize_property(key: &str) -> String {
+    key.replace(['_', '-'], "")
+}
+
+/// Returns the audio property `key` of `audiofile`, or nil if the format
+/// doesn't report it.
+fn property(audiofile: &AudioFile, key: &str) -> Result<Value> {
+    let properties = audiofile.properties();
+
+    let value: Option<Value> = match normalize_property(key).as_str() {
+        "codec" => Some(audiofile.codec().into()),
+        "lossless" => Some(audiofile.is_lossless().into()),
+        "duration" => Some(properties.duration().as_secs_f64().into()),
+        "bitrate" => properties.overall_bitrate().map(|n| f64::from(n).into()),
+        "audiobitrate" => {
+            properties.audio_bitrate().map(|n| f64::from(n).into())
+        }
+        "samplerate" => properties.sample_rate().map(|n| f64::from(n).into()),
+        "bitdepth" => properties.bit_depth().map(|n| f64::from(n).into()),
+        "channels" => properties.channels().map(|n| f64::from(n).into()),
+        _ => bail!("Unknown property :{key}"),
+    };
+
+    Ok(value.unwrap_or(Value::Nil))
+}
+
+/// Checks that every literal key passed to `props` in `node` is a known
+/// property, so typos are reported before any file is processed.
+pub(crate) fn check_properties(node: &mut Node) -> Result<()> {
+    match node.data_mut() {
+        NodeData::Main(nodes) => {
+            for node in nodes {
+                check_properties(node)?;
+            }
+        }
+        NodeData::List { nodes, .. } => {
+            if let [function, key, ..] = nodes.as_mut_slice() {
+                let is_props = matches!(
+                    function.data_mut(),
+                    NodeData::Symbol { module: None, value } if value == "props"
+                );
+
+                if let (true, NodeData::Keyword(key)) =
+                    (is_props, key.data_mut())
+                {
+                    let key = key.trim_start_matches(':');
+
+                    if !PROPERTIES.contains(&normalize_property(key).as_str()) {
+                        bail!("Unknown property :{key}");
+                    }
+                }
+            }
+
+            for node in nodes {
+                check_properties(node)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
 fn align(_: &mut Interpreter, arguments: Arguments) -> TaprResult<Value> {
     let f_width = arguments.unwrap_number(0);
     let string = arguments.unwrap_string(1);
@@ -109,3 +495,157 @@ fn parse_date(_: &mut Interpreter, arguments: Arguments) -> TaprResult<Value> {
 
     Ok(date_string.split('-').next().unwrap().into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::TagOptions;
+    use lofty::{ItemKey, ItemValue, Tag, TagItem, TagType};
+    use std::path::Path;
+
+    fn read(name: &str) -> Result<AudioFile> {
+        read_in("music", name)
+    }
+
+    fn read_in(directory: &str, name: &str) -> Result<AudioFile> {
+        let path = Path::new("tests/testdata").join(directory).join(name);
+
+        AudioFile::new(&path, &TagOptions::new(Vec::new(), Vec::new(), None))
+    }
+
+    fn eval(audiofile: AudioFile, body: &str) -> Result<Value> {
+        let node = Node::from_string(body, "test")?;
+
+        evaluate(Arc::new(audiofile), |intp| node.accept(intp))
+    }
+
+    fn check(body: &str) -> Result<()> {
+        check_properties(&mut Node::from_string(body, "test")?)
+    }
+
+    fn string(value: Value) -> String {
+        let Value::String(string) = value else {
+            panic!("Expected a string.");
+        };
+
+        string
+    }
+
+    fn number(value: Value) -> f64 {
+        let Value::Number(number) = value else {
+            panic!("Expected a number.");
+        };
+
+        number
+    }
+
+    fn assert_number(value: Value, expected: impl Into<f64>) {
+        assert!((number(value) - expected.into()).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_properties() -> Result<()> {
+        let flac = read("Virgo Serena - Damjan Mravunac.flac")?;
+        let mp3 = read("Under Siege - Amon Amarth.mp3")?;
+
+        for audiofile in [&flac, &mp3] {
+            let expected = audiofile.properties();
+
+            assert_number(
+                property(audiofile, "sample-rate")?,
+                expected.sample_rate().unwrap(),
+            );
+            assert_number(
+                property(audiofile, "sample_rate")?,
+                expected.sample_rate().unwrap(),
+            );
+            assert_number(
+                property(audiofile, "channels")?,
+                expected.channels().unwrap(),
+            );
+            assert!(number(property(audiofile, "duration")?) > 0.0);
+        }
+
+        assert!(flac.is_lossless());
+        assert!(!mp3.is_lossless());
+
+        // MP3 doesn't report a bit depth.
+        assert!(matches!(property(&mp3, "bit-depth")?, Value::Nil));
+
+        assert!(property(&flac, "sample-size").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cover_functions() -> Result<()> {
+        let name = "Die Antwoord - Gucci Coochie (feat. Dita Von Teese).mp3";
+        let has_cover = "(if (has-cover) \"yes\" \"no\")";
+
+        assert_eq!(string(eval(read_in("cover", name)?, has_cover)?), "yes");
+        assert_eq!(string(eval(read(name)?, has_cover)?), "no");
+
+        assert_number(eval(read_in("cover", name)?, "(cover-count)")?, 1);
+        assert_number(eval(read(name)?, "(cover-count)")?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_tag_values_match_tags() {
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.insert_unchecked(TagItem::new(
+            ItemKey::Year,
+            ItemValue::Text("1999".to_owned()),
+        ));
+        tag.insert_unchecked(TagItem::new(
+            ItemKey::TrackNumber,
+            ItemValue::Text("3/12".to_owned()),
+        ));
+        tag.insert_unchecked(TagItem::new(
+            ItemKey::DiscNumber,
+            ItemValue::Text("1/2".to_owned()),
+        ));
+
+        let audiofile = AudioFile::from_tags(Path::new("track.mp3"), vec![tag]);
+
+        assert_eq!(all_tag_values(&audiofile, "year"), ["1999"]);
+        assert_eq!(all_tag_values(&audiofile, "track_number"), ["3"]);
+        assert_eq!(all_tag_values(&audiofile, "total_tracks"), ["12"]);
+        assert_eq!(all_tag_values(&audiofile, "disc_number"), ["1"]);
+        assert_eq!(all_tag_values(&audiofile, "total_discs"), ["2"]);
+        assert!(all_tag_values(&audiofile, "genre").is_empty());
+    }
+
+    #[test]
+    fn test_check_properties() {
+        assert!(check("(props :sample-rate)").is_ok());
+        assert!(check("(props  :bit_depth)").is_ok());
+        assert!(check("(props key)").is_ok());
+        assert!(check("(str \"(props :foo)\")").is_ok());
+
+        let error =
+            check("(str (props :samplerate) ( props :foo))").unwrap_err();
+
+        assert_eq!(error.to_string(), "Unknown property :foo");
+    }
+
+    #[test]
+    fn test_unknown_property_at_runtime() -> Result<()> {
+        let audiofile = read("Under Siege - Amon Amarth.mp3")?;
+        let failure = Failure::default();
+
+        let function = PropertiesFunction(Arc::new(audiofile), failure.clone());
+
+        assert!(matches!(function.get("samplerate"), Value::Number(_)));
+        assert!(failure.take().is_none());
+
+        assert!(matches!(function.get("foo"), Value::Nil));
+        assert_eq!(
+            failure.take().unwrap().to_string(),
+            "Unknown property :foo"
+        );
+
+        Ok(())
+    }
+}
@@ -62,7 +62,7 @@ mod tests {
     #[test]
     fn test_harness_with_err() {
         let func = test_function_err;
-        let harness = test_runner(|| Ok(()), |_| Ok(()), func);
+        let harness = test_runner(|| Ok(()), |()| Ok(()), func);
         let bare = func(&());
 
         match bare {
@@ -77,7 +77,7 @@ mod tests {
     #[test]
     fn test_harness_with_ok() {
         let func = test_function_ok;
-        let harness = test_runner(|| Ok(()), |_| Ok(()), func);
+        let harness = test_runner(|| Ok(()), |()| Ok(()), func);
         let bare = func(&());
 
         match bare {
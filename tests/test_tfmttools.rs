@@ -1,4 +1,5 @@
 use anyhow::Result;
+use assert_cmd::assert::Assert;
 use assert_cmd::Command;
 use assert_fs::prelude::*;
 use assert_fs::TempDir;
@@ -11,10 +12,14 @@ use test_harness::test_runner;
 const TEST_DATA_DIRECTORY: &str = "tests/testdata/";
 
 static INITIAL_CONFIG_REFERENCE: Lazy<Vec<String>> = Lazy::new(|| {
-    vec!["config/simple_input.tapr", "config/typical_input.tapr"]
-        .into_iter()
-        .map(normalize_separators)
-        .collect()
+    vec![
+        "config/artist_playlist.tapr",
+        "config/simple_input.tapr",
+        "config/typical_input.tapr",
+    ]
+    .into_iter()
+    .map(normalize_separators)
+    .collect()
 });
 
 static INITIAL_FILE_REFERENCE: Lazy<Vec<String>> = Lazy::new(|| {
@@ -23,6 +28,7 @@ static INITIAL_FILE_REFERENCE: Lazy<Vec<String>> = Lazy::new(|| {
         "files/Dune - MASTER BOOT RECORD.mp3",
         "files/SET MIDI=SYNTH1 MAPG MODE1 - MASTER BOOT RECORD.mp3",
         "files/Under Siege - Amon Amarth.mp3",
+        "files/Virgo Serena - Damjan Mravunac.flac",
         "files/Welcome To Heaven - Damjan Mravunac.ogg",
         "files/While Your Lips Are Still Red - Nightwish.mp3",
     ]
@@ -38,6 +44,7 @@ static TYPICAL_INPUT_REFERENCE: Lazy<Vec<String>> = Lazy::new(|| {
     "myname/MASTER BOOT RECORD/2016.03 - CEDIT AUTOEXEC.BAT/05 - SET MIDI=SYNTH1 MAPG MODE1.mp3",
     "myname/Amon Amarth/2013 - Deceiver of the Gods/105 - Under Siege.mp3",
     "myname/The Talos Principle/2015 - The Talos Principle OST/01 - Damjan Mravunac - Welcome To Heaven.ogg",
+    "myname/The Talos Principle/2015 - The Talos Principle OST/02 - Damjan Mravunac - Virgo Serena.flac",
     "myname/Nightwish/While Your Lips Are Still Red.mp3",
 ]    .into_iter()
 .map(normalize_separators)
@@ -238,6 +245,7 @@ fn test_rename_simple_input() -> Result<()> {
         "MASTER BOOT RECORD/SET MIDI=SYNTH1 MAPG MODE1.mp3",
         "Amon Amarth/Under Siege.mp3",
         "Damjan Mravunac/Welcome To Heaven.ogg",
+        "Damjan Mravunac/Virgo Serena.flac",
         "Nightwish/While Your Lips Are Still Red.mp3",
         "Die Antwoord/Gucci Coochie (feat. Dita Von Teese).mp3",
     ]
@@ -373,6 +381,576 @@ fn test_redo_typical_input() -> Result<()> {
     )
 }
 
+fn mislabel_files(env: &TestEnv) -> Result<()> {
+    let files_dir = env.get_files_dir();
+
+    fs::rename(
+        files_dir.join("Virgo Serena - Damjan Mravunac.flac"),
+        files_dir.join("Virgo Serena - Damjan Mravunac.mp3"),
+    )?;
+
+    fs::rename(
+        files_dir.join("Dune - MASTER BOOT RECORD.mp3"),
+        files_dir.join("Dune - MASTER BOOT RECORD.MP3"),
+    )?;
+
+    Ok(())
+}
+
+fn rename_simple_input(env: &TestEnv, keep_extension: bool) {
+    let config_dir = env.get_config_dir();
+
+    let mut cmd = Command::cargo_bin("tapr").unwrap();
+
+    cmd.arg("--config").arg(config_dir).arg("rename");
+
+    if keep_extension {
+        cmd.arg("--keep-extension");
+    }
+
+    let assert = cmd
+        .arg("simple_input")
+        .current_dir(env.tempdir.path())
+        .assert();
+
+    println!("{}", String::from_utf8_lossy(&assert.get_output().stdout));
+
+    assert.success();
+}
+
+#[test]
+fn test_rename_sniffs_format() -> Result<()> {
+    let reference: Vec<String> = vec![
+        "MASTER BOOT RECORD/Dune.mp3",
+        "Damjan Mravunac/Virgo Serena.flac",
+    ]
+    .into_iter()
+    .map(normalize_separators)
+    .collect();
+
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            mislabel_files(env)?;
+
+            rename_simple_input(env, false);
+
+            env.assert_files_exist(&reference, "assert reference files exist");
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_keep_extension() -> Result<()> {
+    let reference: Vec<String> = vec![
+        "MASTER BOOT RECORD/Dune.MP3",
+        "Damjan Mravunac/Virgo Serena.mp3",
+    ]
+    .into_iter()
+    .map(normalize_separators)
+    .collect();
+
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            mislabel_files(env)?;
+
+            rename_simple_input(env, true);
+
+            env.assert_files_exist(&reference, "assert reference files exist");
+
+            Ok(())
+        },
+    )
+}
+
+fn add_broken_file(env: &TestEnv) -> Result<()> {
+    fs::write(env.get_files_dir().join("Broken.mp3"), "Not an audio file.")?;
+    Ok(())
+}
+
+#[test]
+fn test_rename_skips_broken_files() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            add_broken_file(env)?;
+
+            let mut cmd = Command::cargo_bin("tapr").unwrap();
+
+            let assert = cmd
+                .arg("--config")
+                .arg(env.get_config_dir())
+                .arg("rename")
+                .arg("typical_input")
+                .arg("myname")
+                .current_dir(env.tempdir.path())
+                .assert();
+
+            println!(
+                "{}",
+                String::from_utf8_lossy(&assert.get_output().stdout)
+            );
+
+            assert
+                .success()
+                .stdout(predicate::str::contains("Skipped 1 file:"));
+
+            env.assert_files_exist(
+                &TYPICAL_INPUT_REFERENCE,
+                "assert reference files exist",
+            );
+            env.assert_files_exist(
+                &[normalize_separators("files/Broken.mp3")],
+                "assert broken file was left alone",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_fail_fast() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            add_broken_file(env)?;
+
+            let mut cmd = Command::cargo_bin("tapr").unwrap();
+
+            let assert = cmd
+                .arg("--config")
+                .arg(env.get_config_dir())
+                .arg("rename")
+                .arg("--fail-fast")
+                .arg("typical_input")
+                .arg("myname")
+                .current_dir(env.tempdir.path())
+                .assert();
+
+            println!(
+                "{}",
+                String::from_utf8_lossy(&assert.get_output().stdout)
+            );
+
+            assert
+                .success()
+                .stdout(predicate::str::contains("An error occurred"));
+
+            env.assert_files_exist(
+                &INITIAL_FILE_REFERENCE,
+                "assert initial files are untouched",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+/// Runs tapr with `args` in the temporary directory and prints its output.
+fn tapr(env: &TestEnv, args: &[&str]) -> Assert {
+    let mut cmd = Command::cargo_bin("tapr").unwrap();
+
+    let assert = cmd
+        .arg("--config")
+        .arg(env.get_config_dir())
+        .args(args)
+        .current_dir(env.tempdir.path())
+        .assert();
+
+    println!("{}", String::from_utf8_lossy(&assert.get_output().stdout));
+
+    assert
+}
+
+/// Renames with the `simple_input` script, passing `options` to rename.
+fn rename_simple_input_with(env: &TestEnv, options: &[&str]) -> Assert {
+    let mut args = vec!["rename"];
+    args.extend(options);
+    args.push("simple_input");
+
+    tapr(env, &args)
+}
+
+fn add_sidecar_file(env: &TestEnv) -> Result<()> {
+    fs::write(
+        env.get_files_dir().join("Dune - MASTER BOOT RECORD.lrc"),
+        "[00:00.00] Instrumental",
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_rename_moves_sidecars() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            add_sidecar_file(env)?;
+
+            rename_simple_input(env, false);
+
+            env.assert_files_exist(
+                &[normalize_separators("MASTER BOOT RECORD/Dune.lrc")],
+                "assert sidecar followed its track",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_no_sidecars() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            add_sidecar_file(env)?;
+
+            rename_simple_input_with(env, &["--no-sidecars"]).success();
+
+            env.assert_files_exist(
+                &[normalize_separators("files/Dune - MASTER BOOT RECORD.lrc")],
+                "assert sidecar was left alone",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_removes_leftovers() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            let files_dir = env.get_files_dir();
+
+            let album = files_dir.join("Album");
+            fs::create_dir(&album)?;
+            fs::rename(
+                files_dir.join("Dune - MASTER BOOT RECORD.mp3"),
+                album.join("Dune - MASTER BOOT RECORD.mp3"),
+            )?;
+            fs::write(album.join("Thumbs.db"), "")?;
+
+            let untouched = files_dir.join("untouched");
+            fs::create_dir(&untouched)?;
+            fs::write(untouched.join("cover.jpg"), "")?;
+
+            rename_simple_input_with(
+                env,
+                &["--no-sidecars", "--remove-leftovers"],
+            )
+            .success()
+            .stdout(predicate::str::contains("Removed leftover files."));
+
+            env.assert_files_missing(
+                &[normalize_separators("files/Album")],
+                "assert leftover folder was removed",
+            );
+            env.assert_files_exist(
+                &[normalize_separators("files/untouched/cover.jpg")],
+                "assert untouched folder was left alone",
+            );
+
+            undo(env);
+
+            env.assert_files_exist(
+                &[normalize_separators("files/Album/Thumbs.db")],
+                "assert leftover was restored",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_copy_mode() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            rename_simple_input_with(env, &["--mode", "copy"]).success();
+
+            let copy = [normalize_separators("MASTER BOOT RECORD/Dune.mp3")];
+
+            env.assert_files_exist(&INITIAL_FILE_REFERENCE, "assert originals");
+            env.assert_files_exist(&copy, "assert copy");
+
+            undo(env);
+
+            env.assert_files_exist(&INITIAL_FILE_REFERENCE, "assert originals");
+            env.assert_files_missing(&copy, "assert copy was removed");
+
+            Ok(())
+        },
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn test_rename_hardlink_mode() -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            rename_simple_input_with(env, &["--mode", "hardlink"]).success();
+
+            let original =
+                env.get_files_dir().join("Dune - MASTER BOOT RECORD.mp3");
+            let link = env.path().join("MASTER BOOT RECORD").join("Dune.mp3");
+
+            assert_eq!(
+                fs::metadata(&link)?.ino(),
+                fs::metadata(&original)?.ino()
+            );
+
+            undo(env);
+
+            env.assert_files_exist(&INITIAL_FILE_REFERENCE, "assert originals");
+            assert!(fs::symlink_metadata(&link).is_err());
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_symlink_mode() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            rename_simple_input_with(env, &["--mode", "symlink"]).success();
+
+            let original =
+                env.get_files_dir().join("Dune - MASTER BOOT RECORD.mp3");
+            let link = env.path().join("MASTER BOOT RECORD").join("Dune.mp3");
+
+            assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+            assert_eq!(fs::canonicalize(&link)?, fs::canonicalize(&original)?);
+
+            undo(env);
+
+            env.assert_files_exist(&INITIAL_FILE_REFERENCE, "assert originals");
+            assert!(fs::symlink_metadata(&link).is_err());
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_input_output() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            let inbox = env.path().join("inbox");
+            fs::create_dir(&inbox)?;
+            fs::rename(env.get_files_dir(), inbox.join("Album"))?;
+
+            rename_simple_input_with(
+                env,
+                &["--input", "inbox", "--output", "library"],
+            )
+            .success();
+
+            let source = [normalize_separators(
+                "inbox/Album/Dune - MASTER BOOT RECORD.mp3",
+            )];
+            let target =
+                [normalize_separators("library/MASTER BOOT RECORD/Dune.mp3")];
+
+            env.assert_files_missing(&source, "assert moved");
+            env.assert_files_exist(&target, "assert moved to output");
+            env.assert_files_missing(
+                &[normalize_separators("inbox/Album")],
+                "assert emptied folder was removed",
+            );
+            env.assert_files_exist(
+                &[String::from("inbox")],
+                "assert input was kept",
+            );
+
+            undo(env);
+
+            env.assert_files_exist(&source, "assert restored");
+            env.assert_files_missing(
+                &[String::from("library")],
+                "assert output was removed",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_rename_rewrites_playlists() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            let playlists = env.tempdir.path().join("playlists");
+            let playlist = playlists.join("all.m3u8");
+            let original = "#EXTM3U\n../files/Dune - MASTER BOOT RECORD.mp3\n";
+
+            fs::create_dir(&playlists)?;
+            fs::write(&playlist, original)?;
+
+            rename_simple_input_with(env, &["--playlists", "playlists"])
+                .success();
+
+            let expected = format!(
+                "#EXTM3U\n{}\n",
+                normalize_separators("../MASTER BOOT RECORD/Dune.mp3")
+            );
+
+            assert_eq!(fs::read_to_string(&playlist)?, expected);
+
+            undo(env);
+
+            assert_eq!(fs::read_to_string(&playlist)?, original);
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_playlist() -> Result<()> {
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            tapr(
+                env,
+                &["playlist", "--output", "playlists", "artist_playlist"],
+            )
+            .success();
+
+            let playlist = env
+                .tempdir
+                .path()
+                .join("playlists")
+                .join("MASTER BOOT RECORD.m3u8");
+
+            let expected = format!(
+                "#EXTM3U\n{}\n{}\n",
+                normalize_separators("../files/Dune - MASTER BOOT RECORD.mp3"),
+                normalize_separators(
+                    "../files/SET MIDI=SYNTH1 MAPG MODE1 - MASTER BOOT RECORD.mp3"
+                )
+            );
+
+            assert_eq!(fs::read_to_string(playlist)?, expected);
+            env.assert_files_exist(&INITIAL_FILE_REFERENCE, "assert not moved");
+
+            undo(env);
+
+            env.assert_files_missing(
+                &[String::from("playlists")],
+                "assert playlists were removed",
+            );
+
+            Ok(())
+        },
+    )
+}
+
+const COVER_FILE: &str =
+    "cover/Die Antwoord - Gucci Coochie (feat. Dita Von Teese).mp3";
+
+fn add_covered_files(env: &TestEnv) -> Result<()> {
+    let source = TestEnv::get_test_data_dir().join(COVER_FILE);
+
+    for (album, name) in [
+        ("Album A", "01.mp3"),
+        ("Album A", "02.mp3"),
+        ("Album B", "01.mp3"),
+    ] {
+        let directory = env.path().join("covers").join(album);
+        fs::create_dir_all(&directory)?;
+        fs::copy(&source, directory.join(name))?;
+    }
+
+    Ok(())
+}
+
+fn count_images(directory: &Path) -> Result<usize> {
+    let mut count = 0;
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+
+        if path.extension().is_some_and(|extension| extension == "png") {
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+#[test]
+fn test_extract_covers() -> Result<()> {
+    let reference: Vec<String> =
+        vec!["covers/Album A/cover.png", "covers/Album B/cover.png"]
+            .into_iter()
+            .map(normalize_separators)
+            .collect();
+
+    test_runner(
+        TestEnv::new,
+        |_| Ok(()),
+        |env| {
+            add_covered_files(env)?;
+
+            let mut cmd = Command::cargo_bin("tapr").unwrap();
+
+            let assert = cmd
+                .arg("--config")
+                .arg(env.get_config_dir())
+                .arg("extract-covers")
+                .current_dir(env.path().join("covers"))
+                .assert();
+
+            println!(
+                "{}",
+                String::from_utf8_lossy(&assert.get_output().stdout)
+            );
+
+            assert.success();
+
+            env.assert_files_exist(&reference, "assert covers were extracted");
+
+            let covers = env.path().join("covers");
+
+            assert_eq!(count_images(&covers.join("Album A"))?, 1);
+            assert_eq!(count_images(&covers.join("Album B"))?, 1);
+
+            undo(env);
+
+            env.assert_files_missing(&reference, "assert covers were removed");
+
+            Ok(())
+        },
+    )
+}
+
 /// Normalizes separators for the platform in `string`.
 pub(crate) fn normalize_separators(string: &str) -> String {
     string.replace(